@@ -0,0 +1,97 @@
+//! Calibrate the thresholds of `Workers::Auto`, by timing fixed
+//! numbers of workers on random clouds of increasing size and
+//! reporting the smallest estimated work at which each number of
+//! workers beats a serial calculation.
+//!
+//! Only as many workers as the machine has threads are timed, since
+//! any more would compete for the same threads.
+//!
+//! `serial_below` is the break even point of 2 workers. Since
+//! `Workers::Auto` uses `work / work_per_worker` workers, every
+//! number of workers `k` pays off if `work_per_worker` is at least
+//! the break even point of `k` workers divided by `k`, and the
+//! suggested value is the largest of these for 4, 8 and 16 workers.
+//!
+//! Run with `cargo bench --bench auto_workers`.
+
+use ndarray::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use rusty_hausdorff::*;
+use std::time::{Duration, Instant};
+
+fn time(ar1: &Array2<f64>, ar2: &Array2<f64>, workers: usize) -> Duration {
+    let options = HausdorffOptions::new().workers(workers);
+    let repeats = 1 + 20_000 / (ar1.nrows() * ar2.nrows());
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..repeats {
+                try_directed_hausdorff_with(ar1, ar2, &options).unwrap();
+            }
+            start.elapsed() / repeats as u32
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+    println!("{} threads available", available);
+    let counts: Vec<usize> = [2, 4, 8, 16]
+        .into_iter()
+        .filter(|&workers| workers <= available)
+        .collect();
+    if counts.is_empty() {
+        println!("no parallel calculation to time, so no thresholds to suggest");
+        return;
+    }
+    // a row for each worker, since there are never more workers than rows
+    let (points, point) = (Array2::<f64>::zeros((16, 3)), Array2::<f64>::zeros((1, 3)));
+    let single = time(&points, &point, 1);
+    for &workers in &counts {
+        let start_up = time(&points, &point, workers).saturating_sub(single);
+        println!("start up of {:>2} workers {:>10.2?}", workers, start_up);
+    }
+
+    let mut break_even = vec![None; counts.len()];
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    // about a factor of 2 in work between sizes
+    let sizes = [
+        16, 23, 32, 45, 64, 91, 128, 181, 256, 362, 512, 724, 1024, 1448, 2048,
+    ];
+    for rows in sizes {
+        let ar1 = Array2::from_shape_fn((rows, 3), |_| rng.gen::<f64>());
+        let ar2 = Array2::from_shape_fn((rows, 3), |_| rng.gen::<f64>());
+        let work = (rows * rows * 3) as u64;
+        let serial = time(&ar1, &ar2, 1);
+        print!("work {:>10}: serial {:>10.2?}", work, serial);
+        for (k, &workers) in counts.iter().enumerate() {
+            let parallel = time(&ar1, &ar2, workers);
+            print!("  {:>2} workers {:>10.2?}", workers, parallel);
+            if parallel < serial && break_even[k].is_none() {
+                break_even[k] = Some(work);
+            }
+        }
+        println!();
+    }
+    for (workers, found) in counts.iter().zip(&break_even) {
+        match found {
+            Some(work) => println!("{:>2} workers beat serial from work {}", workers, work),
+            None => println!("{:>2} workers never beat serial", workers),
+        }
+    }
+    let serial_below = break_even[0].unwrap_or(u64::MAX);
+    // the break even points of 4, 8 and 16 workers, per worker,
+    // or of 2 workers on a machine with fewer threads
+    let work_per_worker = counts[1..]
+        .iter()
+        .zip(&break_even[1..])
+        .map(|(&workers, found)| found.map_or(u64::MAX, |work| work / workers as u64))
+        .max()
+        .unwrap_or(serial_below / 2);
+    println!(
+        "suggested: AutoWorkers::new().serial_below({}).work_per_worker({})",
+        serial_below, work_per_worker
+    );
+}
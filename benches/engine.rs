@@ -0,0 +1,45 @@
+//! Compare many small calculations with the free functions, which
+//! spawn threads for every call, against a reused `HausdorffEngine`.
+//!
+//! Run with `cargo bench --bench engine`.
+
+use ndarray::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use rusty_hausdorff::*;
+use std::time::Instant;
+
+fn main() {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let calls = 10_000;
+    for rows in [16, 64, 256] {
+        let clouds: Vec<Array2<f64>> = (0..8)
+            .map(|_| Array2::from_shape_fn((rows, 3), |_| rng.gen()))
+            .collect();
+        for workers in [1, 4] {
+            let options = HausdorffOptions::new().workers(workers);
+            let start = Instant::now();
+            for k in 0..calls {
+                try_directed_hausdorff_with(&clouds[k % 8], &clouds[(k + 1) % 8], &options)
+                    .unwrap();
+            }
+            let free = start.elapsed();
+            let mut engine = HausdorffEngine::new(options);
+            let start = Instant::now();
+            for k in 0..calls {
+                engine
+                    .directed(&clouds[k % 8], &clouds[(k + 1) % 8])
+                    .unwrap();
+            }
+            let reused = start.elapsed();
+            println!(
+                "{:>4} rows, workers = {}: {:>8.2?} per call, engine {:>8.2?} per call ({:.2}x)",
+                rows,
+                workers,
+                free / calls as u32,
+                reused / calls as u32,
+                free.as_secs_f64() / reused.as_secs_f64()
+            );
+        }
+    }
+}
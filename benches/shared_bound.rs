@@ -0,0 +1,80 @@
+//! Compare workers breaking early against their own running maximum
+//! with workers sharing it through an atomic.
+//!
+//! Run with `cargo bench --bench shared_bound`.
+
+use ndarray::prelude::*;
+use ndarray_npy::NpzReader;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use rusty_hausdorff::*;
+use std::fs::File;
+use std::time::{Duration, Instant};
+
+/// The fastest of `repeats` calculations, after checking
+/// that sharing the bound does not change the result.
+fn time(
+    ar1: &Array2<f64>,
+    ar2: &Array2<f64>,
+    options: &HausdorffOptions,
+    repeats: usize,
+) -> Duration {
+    let expected = try_directed_hausdorff_with(ar1, ar2, &options.clone().shared_bound(false));
+    assert_eq!(try_directed_hausdorff_with(ar1, ar2, options), expected);
+    (0..repeats)
+        .map(|_| {
+            let start = Instant::now();
+            try_directed_hausdorff_with(ar1, ar2, options).unwrap();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn compare(name: &str, ar1: &Array2<f64>, ar2: &Array2<f64>, repeats: usize) {
+    for workers in [2, 4, 8] {
+        let options = HausdorffOptions::new().workers(workers);
+        let local = time(ar1, ar2, &options, repeats);
+        let shared = time(ar1, ar2, &options.shared_bound(true), repeats);
+        println!(
+            "{:<32} workers = {}: local {:>10.3?}  shared {:>10.3?}  ({:.2}x)",
+            name,
+            workers,
+            local,
+            shared,
+            local.as_secs_f64() / shared.as_secs_f64()
+        );
+    }
+}
+
+fn random_cloud(rng: &mut ChaChaRng, rows: usize, offset: f64) -> Array2<f64> {
+    Array2::from_shape_fn((rows, 3), |_| rng.gen::<f64>() + offset)
+}
+
+fn main() {
+    let mut npz = NpzReader::new(File::open("src/paths.npz").expect("unable to open data file"))
+        .expect("unable to read from file");
+    let path_1: Array2<f64> = npz.by_name("path_1.npy").unwrap();
+    let path_2: Array2<f64> = npz.by_name("path_2.npy").unwrap();
+    compare("paths.npz forward", &path_1, &path_2, 200);
+    compare("paths.npz reverse", &path_2, &path_1, 200);
+
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    for (rows, repeats) in [(10_000, 5), (100_000, 2)] {
+        let ar1 = random_cloud(&mut rng, rows, 0.0);
+        let ar2 = random_cloud(&mut rng, rows, 0.0);
+        compare(
+            &format!("random {} x {} overlapping", rows, rows),
+            &ar1,
+            &ar2,
+            5,
+        );
+        let ar2 = random_cloud(&mut rng, rows, 0.5);
+        compare(
+            &format!("random {} x {} offset", rows, rows),
+            &ar1,
+            &ar2,
+            repeats,
+        );
+    }
+}
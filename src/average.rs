@@ -0,0 +1,366 @@
+//! The average Hausdorff distances, which average the nearest
+//! neighbour distances of all the points rather than taking
+//! the largest.
+
+use crate::coordinate::{Coordinate, Distance};
+use crate::error::HausdorffError;
+use crate::input::{apply_non_finite_policy, check_shapes, retain_finite_rows};
+use crate::metric::Metric;
+use crate::nearest::nearest_distances;
+use crate::options::{HausdorffOptions, NonFinitePolicy};
+use crate::parallel::Executor;
+use crate::shuffle::RowOrders;
+use ndarray::{ArrayView2, AsArray, Ix2};
+use num_traits::NumCast;
+
+/// Which average of the nearest neighbour distances an average
+/// Hausdorff distance reports.
+///
+/// Writing `d(a, B)` for the distance from a point `a` of `A` to its
+/// nearest neighbour in `B`, and `n(A)` for the number of points:
+///
+/// | | directed, from `A` to `B` | symmetric |
+/// |---|---|---|
+/// | [`mean`](Averaging::mean) | `Σ d(a, B) / n(A)` | `(Σ d(a, B) + Σ d(b, A)) / (n(A) + n(B))` |
+/// | [`modified`](Averaging::modified) | `Σ d(a, B) / n(A)` | `max(Σ d(a, B) / n(A), Σ d(b, A) / n(B))` |
+/// | [`averaged`](Averaging::averaged) | `(Σ d(a, B)^p / n(A))^(1/p)` | the larger of the directed distances |
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Averaging {
+    kind: Kind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Mean,
+    Modified,
+    Averaged(f64),
+}
+
+impl Averaging {
+    /// The average Hausdorff distance, the mean of the nearest
+    /// neighbour distances of the points of both arrays together.
+    pub fn mean() -> Self {
+        Averaging { kind: Kind::Mean }
+    }
+
+    /// The modified Hausdorff distance of M.-P. Dubuisson and A. K.
+    /// Jain, "A modified Hausdorff distance for object matching",
+    /// ICPR 1994, the larger of the means of the nearest neighbour
+    /// distances of either array.
+    pub fn modified() -> Self {
+        Averaging {
+            kind: Kind::Modified,
+        }
+    }
+
+    /// The averaged Hausdorff distance `Δp` of O. Schütze et al.,
+    /// "Using the averaged Hausdorff distance as a performance measure
+    /// in evolutionary multiobjective optimization", IEEE TEVC 2012,
+    /// the larger of the `p`-th power means of the nearest neighbour
+    /// distances of either array. It is the modified Hausdorff
+    /// distance for `p = 1`, and tends to the Hausdorff distance
+    /// as `p` grows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is less than one (or NaN) or infinite.
+    pub fn averaged(p: f64) -> Self {
+        assert!(
+            (1.0..f64::INFINITY).contains(&p),
+            "the averaging exponent must be finite and at least 1, not {}",
+            p
+        );
+        Averaging {
+            kind: Kind::Averaged(p),
+        }
+    }
+
+    fn exponent(self) -> f64 {
+        match self.kind {
+            Kind::Averaged(p) => p,
+            Kind::Mean | Kind::Modified => 1.0,
+        }
+    }
+}
+
+/// Calculate the directed average Hausdorff distance from `ar1`
+/// to `ar2`, as defined by the [`Averaging`] and configured by
+/// [`HausdorffOptions`].
+///
+/// Every point needs its exact nearest neighbour, so unlike the
+/// directed Hausdorff distance there are no early breaks, but the rows
+/// are distributed over the workers in batches in the same way. The
+/// distances are summed in the order of the rows, so the result does
+/// not depend on the number of workers or on the [`Shuffle`](crate::Shuffle).
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0_f64, 1.0], [1.0, 1.0], [2.0, 4.0]]);
+/// let a2 = arr2(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]);
+/// let options = HausdorffOptions::new();
+/// let mean = try_directed_average_hausdorff_with(&a1, &a2, Averaging::mean(), &options);
+/// assert_eq!(mean.unwrap(), 2.0);
+/// let averaged = try_directed_average_hausdorff_with(&a1, &a2, Averaging::averaged(2.0), &options);
+/// assert_eq!(averaged.unwrap(), 6.0_f64.sqrt());
+/// ```
+pub fn try_directed_average_hausdorff_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    averaging: Averaging,
+    options: &HausdorffOptions<M>,
+) -> Result<Distance<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    average_with(
+        ar1.into(),
+        ar2.into(),
+        averaging,
+        false,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// Calculate the (symmetric) average Hausdorff distance between
+/// `ar1` and `ar2`, as defined by the [`Averaging`] and configured
+/// by [`HausdorffOptions`].
+///
+/// Both directions are calculated as by
+/// [`try_directed_average_hausdorff_with`], and combined as the
+/// [`Averaging`] defines.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0_f64, 1.0], [1.0, 1.0], [2.0, 4.0]]);
+/// let a2 = arr2(&[[0.0, 0.0], [2.0, 0.0]]);
+/// let options = HausdorffOptions::new();
+/// // the nearest neighbour distances are 1, √2 and 4 from a1, and 1 and √2 from a2
+/// let mean = try_average_hausdorff_with(&a1, &a2, Averaging::mean(), &options).unwrap();
+/// assert!((mean - (6.0 + 2.0 * 2.0_f64.sqrt()) / 5.0).abs() < 1e-12);
+/// let modified = try_average_hausdorff_with(&a1, &a2, Averaging::modified(), &options).unwrap();
+/// assert!((modified - (5.0 + 2.0_f64.sqrt()) / 3.0).abs() < 1e-12);
+/// ```
+pub fn try_average_hausdorff_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    averaging: Averaging,
+    options: &HausdorffOptions<M>,
+) -> Result<Distance<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    average_with(
+        ar1.into(),
+        ar2.into(),
+        averaging,
+        true,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// The calculation behind the free functions and the corresponding
+/// [`HausdorffEngine`](crate::HausdorffEngine) methods, of the
+/// directed distance or, if `symmetric`, of the symmetric one.
+pub(crate) fn average_with<T: Coordinate, M: Metric<T>>(
+    ar1: ArrayView2<T>,
+    ar2: ArrayView2<T>,
+    averaging: Averaging,
+    symmetric: bool,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+    orders: &mut RowOrders,
+) -> Result<Distance<T>, HausdorffError> {
+    check_shapes(&ar1, &ar2)?;
+    let metric = &options.metric;
+    metric.check_columns(ar1.ncols())?;
+    orders.prepare_forward(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    if symmetric {
+        orders.prepare_reverse(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    }
+    let RowOrders {
+        rows1,
+        inner_rows2,
+        rows2,
+        inner_rows1,
+        ..
+    } = orders;
+    let policy = options.non_finite_policy;
+    if let Some(result) = apply_non_finite_policy(&ar1, &ar2, metric, policy, rows1, inner_rows2)? {
+        return Ok(result.distance);
+    }
+    if symmetric && policy == NonFinitePolicy::Skip {
+        retain_finite_rows("ar2", &ar2, rows2)?;
+        retain_finite_rows("ar1", &ar1, inner_rows1)?;
+    }
+
+    let p = averaging.exponent();
+    let mut forward = nearest_distances(&ar1, &ar2, rows1, inner_rows2, options, executor)?;
+    let mean = if symmetric {
+        let reverse = nearest_distances(&ar2, &ar1, rows2, inner_rows1, options, executor)?;
+        match averaging.kind {
+            Kind::Mean => {
+                forward.extend(reverse);
+                power_mean(&forward, p)
+            }
+            Kind::Modified | Kind::Averaged(_) => {
+                power_mean(&forward, p).max(power_mean(&reverse, p))
+            }
+        }
+    } else {
+        power_mean(&forward, p)
+    };
+    Ok(NumCast::from(mean).expect("a representable distance"))
+}
+
+/// The power mean of the `distances` with exponent `p`.
+fn power_mean(distances: &[f64], p: f64) -> f64 {
+    let n = distances.len() as f64;
+    if p == 1.0 {
+        return distances.iter().sum::<f64>() / n;
+    }
+    // scaled by the largest distance, so that large exponents cannot overflow
+    let largest = distances.iter().fold(0.0, |a: f64, &d| a.max(d));
+    if largest == 0.0 || !largest.is_finite() {
+        return largest;
+    }
+    let sum = distances.iter().map(|d| (d / largest).powf(p)).sum::<f64>();
+    largest * (sum / n).powf(p.recip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Shuffle;
+    use ndarray::prelude::*;
+    use rand::{Rng, SeedableRng};
+
+    fn brute_force(a1: &Array2<i16>, a2: &Array2<i16>) -> Vec<f64> {
+        a1.rows()
+            .into_iter()
+            .map(|x| {
+                a2.rows()
+                    .into_iter()
+                    .map(|y| (&x - &y).mapv(|c| c as f64 * c as f64).sum().sqrt())
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_definitions() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(19);
+        let a1 = Array2::from_shape_fn((200, 3), |_| rng.gen_range(-50_i16..50));
+        let a2 = Array2::from_shape_fn((70, 3), |_| rng.gen_range(-50_i16..50));
+        let forward = brute_force(&a1, &a2);
+        let reverse = brute_force(&a2, &a1);
+        let mean = |d: &[f64], p: f64| {
+            (d.iter().map(|d| d.powf(p)).sum::<f64>() / d.len() as f64).powf(p.recip())
+        };
+        let pooled = (forward.iter().sum::<f64>() + reverse.iter().sum::<f64>()) / 270.0;
+        let cases = [
+            (Averaging::mean(), mean(&forward, 1.0), pooled),
+            (
+                Averaging::modified(),
+                mean(&forward, 1.0),
+                mean(&forward, 1.0).max(mean(&reverse, 1.0)),
+            ),
+            (
+                Averaging::averaged(3.0),
+                mean(&forward, 3.0),
+                mean(&forward, 3.0).max(mean(&reverse, 3.0)),
+            ),
+        ];
+        let serial = HausdorffOptions::new().workers(1);
+        for (averaging, directed, symmetric) in cases {
+            let expected = try_directed_average_hausdorff_with(&a1, &a2, averaging, &serial);
+            assert!((*expected.as_ref().unwrap() - directed).abs() < 1e-9);
+            let expected_symmetric = try_average_hausdorff_with(&a1, &a2, averaging, &serial);
+            assert!((*expected_symmetric.as_ref().unwrap() - symmetric).abs() < 1e-9);
+            // bit for bit the same, however the work is divided
+            for workers in [0, 2, 5] {
+                for shuffle in [Shuffle::SciPy(1), Shuffle::Disabled] {
+                    let options = serial.clone().workers(workers).shuffle(shuffle);
+                    assert_eq!(
+                        try_directed_average_hausdorff_with(&a1, &a2, averaging, &options),
+                        expected
+                    );
+                    assert_eq!(
+                        try_average_hausdorff_with(&a1, &a2, averaging, &options),
+                        expected_symmetric
+                    );
+                }
+            }
+        }
+        // the averaged distance approaches the Hausdorff distance
+        let hausdorff = forward
+            .iter()
+            .chain(&reverse)
+            .fold(0.0, |a: f64, &b| a.max(b));
+        let averaged =
+            try_average_hausdorff_with(&a1, &a2, Averaging::averaged(500.0), &serial).unwrap();
+        assert!(averaged <= hausdorff);
+        assert!(averaged > 0.98 * hausdorff);
+    }
+
+    #[test]
+    fn dubuisson_jain_definition() {
+        // the arrays of SciPy's test_hausdorff.py::TestHausdorff::test_indices,
+        // with the distances worked out by hand: from a1, √145, 0, 0, √40 and 1,
+        // and from a2, 0, 0, √8650 and √53, where √8650 = 93.00537618869137
+        // is SciPy's directed_hausdorff(a2, a1)
+        let (a1, a2) = crate::fixtures::indices();
+        let forward = 145_f64.sqrt() + 40_f64.sqrt() + 1.0;
+        let reverse = 8650_f64.sqrt() + 53_f64.sqrt();
+        let options = HausdorffOptions::new();
+        let average = |averaging| -> Result<f64, _> {
+            try_average_hausdorff_with(&a1, &a2, averaging, &options)
+        };
+        // d6 of Dubuisson and Jain, the larger of the mean distances
+        let modified = average(Averaging::modified()).unwrap();
+        assert!((modified - reverse / 4.0).abs() < 1e-12);
+        let directed: Result<f64, _> =
+            try_directed_average_hausdorff_with(&a1, &a2, Averaging::modified(), &options);
+        assert!((directed.unwrap() - forward / 5.0).abs() < 1e-12);
+        let mean = average(Averaging::mean()).unwrap();
+        assert!((mean - (forward + reverse) / 9.0).abs() < 1e-12);
+        // Δ2 of Schütze et al., the larger of the root mean squares
+        let averaged = average(Averaging::averaged(2.0)).unwrap();
+        assert!((averaged - f64::sqrt((8650.0 + 53.0) / 4.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn non_finite_policies() {
+        let a1 = arr2(&[[0.0_f32, 0.0], [f32::NAN, 1.0], [0.0, 10.0]]);
+        let a2 = arr2(&[[0.0, 1.0], [0.0, 2.0]]);
+        let options = HausdorffOptions::new();
+        assert!(try_average_hausdorff_with(&a1, &a2, Averaging::mean(), &options).is_err());
+        let options = options.non_finite_policy(NonFinitePolicy::Skip);
+        let actual = try_directed_average_hausdorff_with(&a1, &a2, Averaging::mean(), &options);
+        assert_eq!(actual, Ok(4.5));
+        // from a2, the distances are 1 and 2
+        let actual = try_average_hausdorff_with(&a1, &a2, Averaging::mean(), &options);
+        assert_eq!(actual, Ok(3.0));
+        let options = options.non_finite_policy(NonFinitePolicy::Propagate);
+        let actual = try_average_hausdorff_with(&a1, &a2, Averaging::modified(), &options);
+        assert!(actual.unwrap().is_nan());
+        assert!(std::panic::catch_unwind(|| Averaging::averaged(0.5)).is_err());
+    }
+}
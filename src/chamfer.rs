@@ -0,0 +1,324 @@
+//! The Chamfer distance, the sum or mean of the (squared) nearest
+//! neighbour distances, as used for point cloud losses.
+
+use crate::coordinate::{Coordinate, Distance};
+use crate::error::HausdorffError;
+use crate::input::{apply_non_finite_policy, check_shapes, retain_finite_rows};
+use crate::metric::Metric;
+use crate::nearest::nearest_distances;
+use crate::options::{HausdorffOptions, NonFinitePolicy};
+use crate::parallel::Executor;
+use crate::shuffle::RowOrders;
+use ndarray::{ArrayView2, AsArray, Ix2};
+use num_traits::NumCast;
+
+/// How a Chamfer distance reduces the nearest neighbour distances
+/// of the points of an array to a single value.
+///
+/// Writing `d(a, B)` for the distance from a point `a` of `A` to its
+/// nearest neighbour in `B`, and `n(A)` for the number of points:
+///
+/// | | directed, from `A` to `B` | bidirectional |
+/// |---|---|---|
+/// | [`sum`](Chamfer::sum) | `Σ d(a, B)` | `Σ d(a, B) + Σ d(b, A)` |
+/// | [`mean`](Chamfer::mean) | `Σ d(a, B) / n(A)` | `Σ d(a, B) / n(A) + Σ d(b, A) / n(B)` |
+///
+/// With [`squared`](Chamfer::squared), every `d` is squared first,
+/// as in the usual point cloud losses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Chamfer {
+    reduction: Reduction,
+    squared: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Reduction {
+    Sum,
+    Mean,
+}
+
+impl Chamfer {
+    /// The sum of the nearest neighbour distances.
+    pub fn sum() -> Self {
+        Chamfer {
+            reduction: Reduction::Sum,
+            squared: false,
+        }
+    }
+
+    /// The mean of the nearest neighbour distances, so that
+    /// the distance does not grow with the number of points.
+    pub fn mean() -> Self {
+        Chamfer {
+            reduction: Reduction::Mean,
+            squared: false,
+        }
+    }
+
+    /// Whether to square the nearest neighbour distances
+    /// before reducing them, by default `false`.
+    pub fn squared(mut self, squared: bool) -> Self {
+        self.squared = squared;
+        self
+    }
+
+    fn reduce(self, distances: &[f64]) -> f64 {
+        let sum = if self.squared {
+            distances.iter().map(|d| d * d).sum()
+        } else {
+            distances.iter().sum::<f64>()
+        };
+        match self.reduction {
+            Reduction::Sum => sum,
+            Reduction::Mean => sum / distances.len() as f64,
+        }
+    }
+}
+
+/// Calculate the directed Chamfer distance from `ar1` to
+/// `ar2`, as defined by the [`Chamfer`] and configured by
+/// [`HausdorffOptions`].
+///
+/// Every point needs its exact nearest neighbour, so unlike the
+/// directed Hausdorff distance there are no early breaks and the
+/// calculation takes time proportional to the product of the numbers
+/// of rows. The rows are distributed over the workers in batches in
+/// the same way, and the distances are summed in the order of the
+/// rows, so the result does not depend on the number of workers or
+/// on the [`Shuffle`](crate::Shuffle).
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0_f64, 1.0], [1.0, 1.0], [2.0, 4.0]]);
+/// let a2 = arr2(&[[0.0, 0.0], [2.0, 0.0]]);
+/// let options = HausdorffOptions::new();
+/// // the nearest neighbour distances are 1, √2 and 4
+/// let sum = try_directed_chamfer_distance_with(&a1, &a2, Chamfer::sum(), &options);
+/// assert_eq!(sum.unwrap(), 5.0 + 2.0_f64.sqrt());
+/// let chamfer = Chamfer::mean().squared(true);
+/// let mean = try_directed_chamfer_distance_with(&a1, &a2, chamfer, &options);
+/// assert_eq!(mean.unwrap(), 19.0 / 3.0);
+/// ```
+pub fn try_directed_chamfer_distance_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    chamfer: Chamfer,
+    options: &HausdorffOptions<M>,
+) -> Result<Distance<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    chamfer_with(
+        ar1.into(),
+        ar2.into(),
+        chamfer,
+        false,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// Calculate the (bidirectional) Chamfer distance between `ar1`
+/// and `ar2`, as defined by the [`Chamfer`] and configured by
+/// [`HausdorffOptions`].
+///
+/// This is the sum of the directed distances in either direction,
+/// each calculated as by [`try_directed_chamfer_distance_with`].
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0_f64, 1.0], [1.0, 1.0], [2.0, 4.0]]);
+/// let a2 = arr2(&[[0.0, 0.0], [2.0, 0.0]]);
+/// let options = HausdorffOptions::new();
+/// // the squared nearest neighbour distances are 1, 2 and 16 from a1, and 1 and 2 from a2
+/// let sum = try_chamfer_distance_with(&a1, &a2, Chamfer::sum().squared(true), &options);
+/// assert_eq!(sum.unwrap(), 22.0);
+/// let mean = try_chamfer_distance_with(&a1, &a2, Chamfer::mean().squared(true), &options);
+/// assert!((mean.unwrap() - (19.0 / 3.0 + 3.0 / 2.0)).abs() < 1e-12);
+/// ```
+pub fn try_chamfer_distance_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    chamfer: Chamfer,
+    options: &HausdorffOptions<M>,
+) -> Result<Distance<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    chamfer_with(
+        ar1.into(),
+        ar2.into(),
+        chamfer,
+        true,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// The calculation behind the free functions and the corresponding
+/// [`HausdorffEngine`](crate::HausdorffEngine) methods, of the
+/// directed distance or, if `bidirectional`, of the sum of both.
+pub(crate) fn chamfer_with<T: Coordinate, M: Metric<T>>(
+    ar1: ArrayView2<T>,
+    ar2: ArrayView2<T>,
+    chamfer: Chamfer,
+    bidirectional: bool,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+    orders: &mut RowOrders,
+) -> Result<Distance<T>, HausdorffError> {
+    check_shapes(&ar1, &ar2)?;
+    let metric = &options.metric;
+    metric.check_columns(ar1.ncols())?;
+    orders.prepare_forward(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    if bidirectional {
+        orders.prepare_reverse(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    }
+    let RowOrders {
+        rows1,
+        inner_rows2,
+        rows2,
+        inner_rows1,
+        ..
+    } = orders;
+    let policy = options.non_finite_policy;
+    if let Some(result) = apply_non_finite_policy(&ar1, &ar2, metric, policy, rows1, inner_rows2)? {
+        return Ok(result.distance);
+    }
+    if bidirectional && policy == NonFinitePolicy::Skip {
+        retain_finite_rows("ar2", &ar2, rows2)?;
+        retain_finite_rows("ar1", &ar1, inner_rows1)?;
+    }
+
+    let forward = nearest_distances(&ar1, &ar2, rows1, inner_rows2, options, executor)?;
+    let mut distance = chamfer.reduce(&forward);
+    if bidirectional {
+        let reverse = nearest_distances(&ar2, &ar1, rows2, inner_rows1, options, executor)?;
+        distance += chamfer.reduce(&reverse);
+    }
+    Ok(NumCast::from(distance).expect("a representable distance"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Manhattan, Shuffle};
+    use ndarray::prelude::*;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(20);
+        let a1 = Array2::from_shape_fn((150, 3), |_| rng.gen_range(-1.0_f32..1.0));
+        let a2 = Array2::from_shape_fn((90, 3), |_| rng.gen_range(-1.0_f32..1.0));
+        let brute_force = |a1: &Array2<f32>, a2: &Array2<f32>, squared: bool| {
+            let nearest = a1.rows().into_iter().map(|x| {
+                let d = a2
+                    .rows()
+                    .into_iter()
+                    .map(|y| (&x - &y).mapv(|c| c.abs() as f64).sum())
+                    .fold(f64::INFINITY, f64::min);
+                if squared {
+                    d * d
+                } else {
+                    d
+                }
+            });
+            nearest.sum::<f64>()
+        };
+        let serial = HausdorffOptions::new().metric(Manhattan).workers(1);
+        for squared in [false, true] {
+            let forward = brute_force(&a1, &a2, squared);
+            let reverse = brute_force(&a2, &a1, squared);
+            let cases = [
+                (Chamfer::sum(), forward, forward + reverse),
+                (
+                    Chamfer::mean(),
+                    forward / 150.0,
+                    forward / 150.0 + reverse / 90.0,
+                ),
+            ];
+            for (chamfer, directed, bidirectional) in cases {
+                let chamfer = chamfer.squared(squared);
+                let expected = try_directed_chamfer_distance_with(&a1, &a2, chamfer, &serial);
+                assert!((*expected.as_ref().unwrap() as f64 - directed).abs() < 1e-3);
+                let expected_both = try_chamfer_distance_with(&a1, &a2, chamfer, &serial);
+                assert!((*expected_both.as_ref().unwrap() as f64 - bidirectional).abs() < 1e-3);
+                // bit for bit the same, however the work is divided
+                for workers in [0, 2, 5] {
+                    for shuffle in [Shuffle::SciPy(2), Shuffle::Disabled] {
+                        let options = serial.clone().workers(workers).shuffle(shuffle);
+                        assert_eq!(
+                            try_directed_chamfer_distance_with(&a1, &a2, chamfer, &options),
+                            expected
+                        );
+                        assert_eq!(
+                            try_chamfer_distance_with(&a1, &a2, chamfer, &options),
+                            expected_both
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pytorch3d_definition() {
+        // pytorch3d.loss.chamfer_distance(x, y) with its defaults, i.e.,
+        // the mean squared nearest neighbour distance from x to y plus
+        // that from y to x, on the arrays of SciPy's test_indices, with
+        // the squared distances worked out by hand: from a1, 145, 0, 0,
+        // 40 and 1, and from a2, 0, 0, 8650 and 53
+        let (a1, a2) = crate::fixtures::indices();
+        let options = HausdorffOptions::new();
+        let loss = Chamfer::mean().squared(true);
+        let actual: f64 = try_chamfer_distance_with(&a1, &a2, loss, &options).unwrap();
+        assert!((actual - (186.0 / 5.0 + 8703.0 / 4.0)).abs() < 1e-9);
+        let actual = try_directed_chamfer_distance_with(&a2, &a1, loss, &options);
+        assert_eq!(actual, Ok(2175.75));
+        let sum = Chamfer::sum().squared(true);
+        assert_eq!(
+            try_chamfer_distance_with(&a1, &a2, sum, &options),
+            Ok(8889.0)
+        );
+    }
+
+    #[test]
+    fn non_finite_policies() {
+        let a1 = arr2(&[[0_u8, 0], [3, 4]]);
+        let a2 = arr2(&[[0_u8, 1], [6, 8]]);
+        let options = HausdorffOptions::new();
+        let chamfer = Chamfer::sum().squared(true);
+        assert_eq!(
+            try_chamfer_distance_with(&a1, &a2, chamfer, &options),
+            Ok(45.0)
+        );
+        let a1 = arr2(&[[0.0, 0.0], [f64::INFINITY, 1.0], [3.0, 4.0]]);
+        let a2 = a2.mapv(|c| c as f64);
+        assert!(try_chamfer_distance_with(&a1, &a2, chamfer, &options).is_err());
+        let options = options.non_finite_policy(NonFinitePolicy::Skip);
+        assert_eq!(
+            try_chamfer_distance_with(&a1, &a2, chamfer, &options),
+            Ok(45.0)
+        );
+        let options = options.non_finite_policy(NonFinitePolicy::Propagate);
+        let actual = try_directed_chamfer_distance_with(&a1, &a2, chamfer, &options);
+        assert!(!actual.unwrap().is_finite());
+    }
+}
@@ -0,0 +1,197 @@
+//! Element types supported by the Hausdorff distance calculations.
+//!
+//! The early break algorithm only ever compares squared distances,
+//! so each coordinate type names the (possibly wider) type in which
+//! squared differences are accumulated, and the square root is
+//! deferred until the very end.
+//!
+//! Integer coordinates accumulate exact squared distances in an
+//! unsigned integer type that cannot overflow for their range, and
+//! are only converted to `f64` for the final square root, so the
+//! results are bit-exact and independent of platform or summation
+//! order.
+
+use num_traits::Float;
+use std::fmt::Debug;
+use std::ops::Add;
+
+/// The type in which squared distances are summed and compared.
+pub trait Accumulator: Copy + PartialOrd + Add<Output = Self> + Send + Sync + Debug {
+    /// The additive identity.
+    const ZERO: Self;
+    /// A value that no squared distance can exceed, used to
+    /// seed each nearest neighbour search.
+    const MAX: Self;
+    /// The floating point type of the final (square rooted) distance.
+    type Distance: Float + Debug + Send + Sync;
+
+    /// Take the square root of an accumulated squared distance.
+    fn sqrt(self) -> Self::Distance;
+
+    /// Convert an accumulated distance to the distance type as is.
+    fn to_distance(self) -> Self::Distance;
+
+    /// Encode a (non-negative) squared distance as bits whose unsigned
+    /// order matches the order of the distances, so that a running
+    /// maximum can be shared between threads through an atomic.
+    fn to_bound_bits(self) -> u64;
+
+    /// Decode bits from [`Accumulator::to_bound_bits`]. The result
+    /// may be rounded down, but never up, so it remains a lower bound.
+    fn from_bound_bits(bits: u64) -> Self;
+}
+
+/// A coordinate type that points may be stored as.
+///
+/// Implemented for `f32` and `f64`, and for `half::f16` when the
+/// `half` feature is enabled, in which case squared distances are
+/// accumulated in `f32`.
+///
+/// Also implemented for the 8, 16 and 32 bit integer types (i.e.,
+/// voxel indices). The 8 and 16 bit types accumulate in `u64`, while
+/// the 32 bit types accumulate in `u128`, since the square of a
+/// single 32 bit difference already approaches `u64::MAX`.
+pub trait Coordinate: Copy + Send + Sync + Debug {
+    /// The type in which squared differences of this
+    /// coordinate type are accumulated.
+    type Accumulator: Accumulator;
+
+    /// The squared difference between two coordinates.
+    fn squared_difference(self, other: Self) -> Self::Accumulator;
+
+    /// The absolute difference between two coordinates.
+    fn abs_difference(self, other: Self) -> Self::Accumulator;
+
+    /// Whether the coordinate is neither NaN nor infinite.
+    fn is_finite(self) -> bool;
+}
+
+/// The distance type returned for points with coordinates of type `T`.
+pub type Distance<T> = <<T as Coordinate>::Accumulator as Accumulator>::Distance;
+
+macro_rules! float_coordinate {
+    ($($float:ty),*) => {
+        $(
+            impl Accumulator for $float {
+                const ZERO: Self = 0.0;
+                const MAX: Self = <$float>::INFINITY;
+                type Distance = $float;
+
+                fn sqrt(self) -> Self::Distance {
+                    <$float>::sqrt(self)
+                }
+
+                fn to_distance(self) -> Self::Distance {
+                    self
+                }
+
+                fn to_bound_bits(self) -> u64 {
+                    // the IEEE 754 bit patterns of non-negative
+                    // floats are ordered like the values
+                    self.to_bits() as u64
+                }
+
+                fn from_bound_bits(bits: u64) -> Self {
+                    <$float>::from_bits(bits as _)
+                }
+            }
+
+            impl Coordinate for $float {
+                type Accumulator = $float;
+
+                fn squared_difference(self, other: Self) -> Self::Accumulator {
+                    (self - other).powi(2)
+                }
+
+                fn abs_difference(self, other: Self) -> Self::Accumulator {
+                    (self - other).abs()
+                }
+
+                fn is_finite(self) -> bool {
+                    <$float>::is_finite(self)
+                }
+            }
+        )*
+    };
+}
+
+float_coordinate!(f32, f64);
+
+macro_rules! integer_accumulator {
+    ($($int:ty),*) => {
+        $(
+            impl Accumulator for $int {
+                const ZERO: Self = 0;
+                const MAX: Self = <$int>::MAX;
+                type Distance = f64;
+
+                fn sqrt(self) -> Self::Distance {
+                    (self as f64).sqrt()
+                }
+
+                fn to_distance(self) -> Self::Distance {
+                    self as f64
+                }
+
+                fn to_bound_bits(self) -> u64 {
+                    // saturating, which only loosens the bound
+                    u64::try_from(self).unwrap_or(u64::MAX)
+                }
+
+                fn from_bound_bits(bits: u64) -> Self {
+                    bits as $int
+                }
+            }
+        )*
+    };
+}
+
+integer_accumulator!(u64, u128);
+
+macro_rules! integer_coordinate {
+    ($accumulator:ty, $wide:ty; $($int:ty),*) => {
+        $(
+            impl Coordinate for $int {
+                type Accumulator = $accumulator;
+
+                fn squared_difference(self, other: Self) -> Self::Accumulator {
+                    let diff = self.abs_difference(other);
+                    diff * diff
+                }
+
+                fn abs_difference(self, other: Self) -> Self::Accumulator {
+                    // the difference of two values is exactly
+                    // representable in the wider signed type
+                    (self as $wide - other as $wide).unsigned_abs() as $accumulator
+                }
+
+                fn is_finite(self) -> bool {
+                    true
+                }
+            }
+        )*
+    };
+}
+
+integer_coordinate!(u64, i32; u8, u16, i8, i16);
+integer_coordinate!(u128, i64; u32, i32);
+
+#[cfg(feature = "half")]
+impl Coordinate for half::f16 {
+    // half precision storage only, accumulating in single
+    // precision avoids overflow beyond ~65504 and the
+    // rounding of every partial sum
+    type Accumulator = f32;
+
+    fn squared_difference(self, other: Self) -> Self::Accumulator {
+        (self.to_f32() - other.to_f32()).powi(2)
+    }
+
+    fn abs_difference(self, other: Self) -> Self::Accumulator {
+        (self.to_f32() - other.to_f32()).abs()
+    }
+
+    fn is_finite(self) -> bool {
+        half::f16::is_finite(self)
+    }
+}
@@ -0,0 +1,444 @@
+//! Hausdorff distances bounded by a cutoff, abandoning the
+//! calculation once the distance is known to exceed it.
+
+use crate::coordinate::{Accumulator, Coordinate, Distance};
+use crate::early_break::{directed_hausdorff_core, merge, SharedBound, Witness};
+use crate::error::HausdorffError;
+use crate::input::{apply_non_finite_policy, check_shapes, retain_finite_rows};
+use crate::metric::Metric;
+use crate::options::{HausdorffOptions, NonFinitePolicy};
+use crate::parallel::{estimated_work, Batches, Executor};
+use crate::result::{Bounded, Direction, HausdorffResult, SymmetricHausdorffResult};
+use crate::shuffle::RowOrders;
+use ndarray::{ArrayView2, AsArray, Ix2};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Calculate the directed Hausdorff distance from `ar1` to `ar2`
+/// if it is at most `max_distance`, as configured by [`HausdorffOptions`],
+/// e.g. to compare a shape only with the candidates nearer than the best
+/// match found so far.
+///
+/// As soon as the running maximum of any worker exceeds `max_distance`,
+/// every worker stops at its next row and the largest distance found so
+/// far is reported as a lower bound. Which rows have been searched by
+/// then depends on the timing of the workers, but the outcome does not,
+/// and a distance within the cutoff is exactly
+/// [`try_directed_hausdorff_with`](crate::try_directed_hausdorff_with).
+/// If non-finite values propagate, the cutoff counts as exceeded, with
+/// the NaN result of `try_directed_hausdorff_with` as the lower bound.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0, 1.0], [1.0, 5.0], [2.0, 2.0]]);
+/// let a2 = arr2(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]);
+/// let options = HausdorffOptions::new();
+/// match try_directed_hausdorff_bounded_with(&a1, &a2, 10.0, &options).unwrap() {
+///     Bounded::Exact(result) => assert_eq!(result.distance, 5.0),
+///     Bounded::ExceedsCutoff { .. } => unreachable!(),
+/// }
+/// match try_directed_hausdorff_bounded_with(&a1, &a2, 1.5, &options).unwrap() {
+///     Bounded::ExceedsCutoff { lower_bound, .. } => assert!(lower_bound.distance > 1.5),
+///     Bounded::Exact(_) => unreachable!(),
+/// }
+/// ```
+pub fn try_directed_hausdorff_bounded_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    max_distance: Distance<T>,
+    options: &HausdorffOptions<M>,
+) -> Result<Bounded<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    let bounded = bounded_with(
+        ar1.into(),
+        ar2.into(),
+        max_distance,
+        false,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )?;
+    Ok(into_directed(bounded))
+}
+
+/// Calculate the (symmetric) Hausdorff distance between `ar1` and
+/// `ar2` if it is at most `max_distance`, as configured by
+/// [`HausdorffOptions`].
+///
+/// The forward direction is calculated first, as by
+/// [`try_directed_hausdorff_bounded_with`], and the reverse direction
+/// only if the forward distance is within the cutoff. A distance
+/// within the cutoff is exactly [`try_hausdorff_with`](crate::try_hausdorff_with)
+/// without [`symmetric_pruning`](HausdorffOptions::symmetric_pruning).
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[1.0, 0.0], [0.0, 1.0], [-1.0, 0.0], [0.0, -1.0]]);
+/// let a2 = arr2(&[[2.0, 0.0], [0.0, 2.0], [-2.0, 0.0], [0.0, -4.0]]);
+/// let options = HausdorffOptions::new();
+/// match try_hausdorff_bounded_with(&a1, &a2, 2.5, &options).unwrap() {
+///     Bounded::ExceedsCutoff { direction, lower_bound } => {
+///         assert_eq!(direction, Direction::Reverse);
+///         assert_eq!(lower_bound.distance, 3.0);
+///     }
+///     Bounded::Exact(_) => unreachable!(),
+/// }
+/// ```
+pub fn try_hausdorff_bounded_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    max_distance: Distance<T>,
+    options: &HausdorffOptions<M>,
+) -> Result<Bounded<T, SymmetricHausdorffResult<T>>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    bounded_with(
+        ar1.into(),
+        ar2.into(),
+        max_distance,
+        true,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// The calculation behind the free functions and the corresponding
+/// [`HausdorffEngine`](crate::HausdorffEngine) methods, of the directed
+/// distance (as the forward result, with no reverse result) or, if
+/// `symmetric`, of the symmetric one.
+pub(crate) fn bounded_with<T: Coordinate, M: Metric<T>>(
+    ar1: ArrayView2<T>,
+    ar2: ArrayView2<T>,
+    max_distance: Distance<T>,
+    symmetric: bool,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+    orders: &mut RowOrders,
+) -> Result<Bounded<T, SymmetricHausdorffResult<T>>, HausdorffError> {
+    check_shapes(&ar1, &ar2)?;
+    let metric = &options.metric;
+    metric.check_columns(ar1.ncols())?;
+    orders.prepare_forward(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    if symmetric {
+        orders.prepare_reverse(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    }
+    let RowOrders {
+        rows1,
+        inner_rows2,
+        rows2,
+        inner_rows1,
+        ..
+    } = orders;
+    let policy = options.non_finite_policy;
+    if let Some(result) = apply_non_finite_policy(&ar1, &ar2, metric, policy, rows1, inner_rows2)? {
+        // a NaN distance is never within the cutoff
+        return Ok(Bounded::ExceedsCutoff {
+            direction: Direction::Forward,
+            lower_bound: result,
+        });
+    }
+    if symmetric && policy == NonFinitePolicy::Skip {
+        retain_finite_rows("ar2", &ar2, rows2)?;
+        retain_finite_rows("ar1", &ar1, inner_rows1)?;
+    }
+
+    let ((value, i, j), exceeded) = bounded_rows(
+        &ar1,
+        &ar2,
+        rows1,
+        inner_rows2,
+        max_distance,
+        options,
+        executor,
+    )?;
+    let forward = HausdorffResult::new(&ar1, &ar2, metric, value, i, j);
+    if exceeded {
+        return Ok(Bounded::ExceedsCutoff {
+            direction: Direction::Forward,
+            lower_bound: forward,
+        });
+    }
+    if !symmetric {
+        return Ok(Bounded::Exact(SymmetricHausdorffResult::new(forward, None)));
+    }
+    let ((value, i, j), exceeded) = bounded_rows(
+        &ar2,
+        &ar1,
+        rows2,
+        inner_rows1,
+        max_distance,
+        options,
+        executor,
+    )?;
+    let reverse = HausdorffResult::new(&ar2, &ar1, metric, value, i, j);
+    if exceeded {
+        return Ok(Bounded::ExceedsCutoff {
+            direction: Direction::Reverse,
+            lower_bound: reverse,
+        });
+    }
+    Ok(Bounded::Exact(SymmetricHausdorffResult::new(
+        forward,
+        Some(reverse),
+    )))
+}
+
+/// The directed outcome of [`bounded_with`], without a `reverse` result.
+pub(crate) fn into_directed<T: Coordinate>(
+    bounded: Bounded<T, SymmetricHausdorffResult<T>>,
+) -> Bounded<T> {
+    match bounded {
+        Bounded::Exact(result) => Bounded::Exact(result.forward),
+        Bounded::ExceedsCutoff {
+            direction,
+            lower_bound,
+        } => Bounded::ExceedsCutoff {
+            direction,
+            lower_bound,
+        },
+    }
+}
+
+/// The farthest of the `rows` of `ar1` from the `inner_rows` of `ar2`,
+/// as for the directed distance, and whether it is farther than
+/// `max_distance`, in which case it is only the farthest row found
+/// before the workers stopped.
+fn bounded_rows<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    rows: &[usize],
+    inner_rows: &[usize],
+    max_distance: Distance<T>,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+) -> Result<(Witness<T::Accumulator>, bool), HausdorffError> {
+    let metric = &options.metric;
+    let work = estimated_work(rows.len(), inner_rows.len(), ar1.ncols());
+    let available = executor.available_workers();
+    let workers = options.workers.resolve(rows.len(), work, available);
+    let batches = Batches::new(rows.len(), workers);
+    let cutoff = Cutoff {
+        max_distance,
+        exceeded: AtomicBool::new(false),
+    };
+    let result = if workers <= 1 {
+        // single thread/serial approach
+        let found = bounded_batches(ar1, ar2, metric, rows, inner_rows, &batches, None, &cutoff);
+        merge([found])
+    } else {
+        let shared = SharedBound::new(T::Accumulator::ZERO);
+        let shared = options.shared_bound.then_some(&shared);
+        merge(executor.run_workers(workers, |_| {
+            bounded_batches(
+                ar1, ar2, metric, rows, inner_rows, &batches, shared, &cutoff,
+            )
+        })?)
+    };
+    let exceeded = cutoff.exceeded.load(Ordering::Relaxed);
+    Ok((result.expect("non-empty rows"), exceeded))
+}
+
+/// The distance beyond which the workers stop, and
+/// whether any of them has found a row beyond it.
+struct Cutoff<D> {
+    max_distance: D,
+    exceeded: AtomicBool,
+}
+
+/// Search batches of the `rows` of `ar1` as
+/// [`directed_hausdorff_batches`](crate::early_break::directed_hausdorff_batches)
+/// would, but a row at a time, stopping as soon as the `cutoff`
+/// is exceeded by this or any other worker.
+#[allow(clippy::too_many_arguments)]
+fn bounded_batches<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+    rows: &[usize],
+    inner_rows: &[usize],
+    batches: &Batches,
+    shared: Option<&SharedBound<T::Accumulator>>,
+    cutoff: &Cutoff<Distance<T>>,
+) -> Option<(Witness<T::Accumulator>, usize)> {
+    let mut cmax = T::Accumulator::ZERO;
+    let mut found = None;
+    while let Some(batch) = batches.next_batch() {
+        let start = batch.start;
+        for position in batch {
+            if cutoff.exceeded.load(Ordering::Relaxed) {
+                return found;
+            }
+            let row = &rows[position..=position];
+            if let Some(witness) =
+                directed_hausdorff_core(ar1, ar2, metric, row, inner_rows, cmax, shared)
+            {
+                cmax = witness.0;
+                found = Some((witness, start));
+                // the metric's final transform is only
+                // needed when the running maximum grows
+                if metric.distance(cmax) > cutoff.max_distance {
+                    cutoff.exceeded.store(true, Ordering::Relaxed);
+                    return found;
+                }
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, Shuffle};
+    use crate::{try_directed_hausdorff_with, try_hausdorff_with, try_nearest_neighbours_with};
+    use ndarray::prelude::*;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn matches_exact_distances() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(25);
+        let a1 = Array2::from_shape_fn((400, 3), |_| rng.gen_range(-1.0..1.0));
+        let a2 = Array2::from_shape_fn((150, 3), |_| rng.gen_range(-1.0..1.0));
+        let (forward_nearest, _) =
+            try_nearest_neighbours_with(&a1, &a2, &HausdorffOptions::new()).unwrap();
+        let (reverse_nearest, _) =
+            try_nearest_neighbours_with(&a2, &a1, &HausdorffOptions::new()).unwrap();
+        for workers in [1, 2, 5] {
+            for shuffle in [Shuffle::default(), Shuffle::SciPy(7), Shuffle::Disabled] {
+                let options = HausdorffOptions::new().workers(workers).shuffle(shuffle);
+                let directed = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+                let symmetric = try_hausdorff_with(&a1, &a2, &options).unwrap();
+                let reverse = symmetric.reverse.as_ref().unwrap().distance;
+                for max_distance in [0.0, directed.distance / 2.0, directed.distance, 2.0] {
+                    let bounded =
+                        try_directed_hausdorff_bounded_with(&a1, &a2, max_distance, &options)
+                            .unwrap();
+                    match &bounded {
+                        Bounded::Exact(result) => {
+                            assert!(directed.distance <= max_distance);
+                            assert_eq!(result, &directed);
+                        }
+                        Bounded::ExceedsCutoff {
+                            direction,
+                            lower_bound,
+                        } => {
+                            assert!(directed.distance > max_distance);
+                            assert_eq!(direction, &Direction::Forward);
+                            assert!(lower_bound.distance > max_distance);
+                            let nearest = forward_nearest[[lower_bound.ar1_index]];
+                            assert_eq!(lower_bound.distance, nearest);
+                        }
+                    }
+
+                    let bounded = try_hausdorff_bounded_with(&a1, &a2, max_distance, &options);
+                    match bounded.unwrap() {
+                        Bounded::Exact(result) => {
+                            assert!(symmetric.distance <= max_distance);
+                            assert_eq!(result, symmetric);
+                        }
+                        Bounded::ExceedsCutoff {
+                            direction,
+                            lower_bound,
+                        } => {
+                            assert!(lower_bound.distance > max_distance);
+                            let nearest = match direction {
+                                Direction::Forward => &forward_nearest,
+                                Direction::Reverse => {
+                                    assert!(directed.distance <= max_distance);
+                                    assert!(reverse > max_distance);
+                                    &reverse_nearest
+                                }
+                            };
+                            assert_eq!(lower_bound.distance, nearest[[lower_bound.ar1_index]]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scipy_reference_values() {
+        // exact at the SciPy distance, and a lower bound below it
+        let (path_1, path_2) = fixtures::paths();
+        let (forward, reverse) = (fixtures::PATHS_FORWARD, fixtures::PATHS_REVERSE);
+        for workers in [1, 4] {
+            let options = HausdorffOptions::new().workers(workers);
+            let bounded = |max_distance| {
+                try_hausdorff_bounded_with(&path_1, &path_2, max_distance, &options).unwrap()
+            };
+            match bounded(reverse) {
+                Bounded::Exact(result) => {
+                    assert_eq!(result.distance, reverse);
+                    assert_eq!(result.forward.distance, forward);
+                }
+                Bounded::ExceedsCutoff { .. } => panic!("the cutoff is the SciPy distance"),
+            }
+            match bounded(2.3) {
+                Bounded::ExceedsCutoff {
+                    direction,
+                    lower_bound,
+                } => {
+                    assert_eq!(direction, Direction::Reverse);
+                    assert_eq!(lower_bound.distance, reverse);
+                }
+                Bounded::Exact(_) => panic!("2.3 is below the SciPy distance"),
+            }
+            match bounded(1.0) {
+                Bounded::ExceedsCutoff {
+                    direction,
+                    lower_bound,
+                } => {
+                    assert_eq!(direction, Direction::Forward);
+                    assert!(lower_bound.distance > 1.0);
+                    assert!(lower_bound.distance <= forward);
+                }
+                Bounded::Exact(_) => panic!("1.0 is below the SciPy distance"),
+            }
+        }
+    }
+
+    #[test]
+    fn non_finite_policies() {
+        let a1 = arr2(&[[0.0, 0.0], [f64::NAN, 1.0], [3.0, 4.0]]);
+        let a2 = arr2(&[[0.0, 0.0]]);
+        let options = HausdorffOptions::new();
+        assert!(try_hausdorff_bounded_with(&a1, &a2, 1.0, &options).is_err());
+        let options = options.non_finite_policy(NonFinitePolicy::Skip);
+        let bounded = try_directed_hausdorff_bounded_with(&a1, &a2, 1.0, &options).unwrap();
+        assert!(!bounded.is_exact());
+        let expected = try_hausdorff_with(&a1, &a2, &options).unwrap();
+        let bounded = try_hausdorff_bounded_with(&a1, &a2, 5.0, &options);
+        assert_eq!(bounded, Ok(Bounded::Exact(expected)));
+        let options = options.non_finite_policy(NonFinitePolicy::Propagate);
+        let expected = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+        // however large the cutoff
+        match try_hausdorff_bounded_with(&a1, &a2, f64::INFINITY, &options).unwrap() {
+            Bounded::ExceedsCutoff {
+                direction,
+                lower_bound,
+            } => {
+                assert_eq!(direction, Direction::Forward);
+                assert_eq!(format!("{lower_bound:?}"), format!("{expected:?}"));
+            }
+            Bounded::Exact(_) => panic!("NaN distances are never within a cutoff"),
+        }
+    }
+}
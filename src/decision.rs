@@ -0,0 +1,428 @@
+//! Decide whether a Hausdorff distance is within a tolerance,
+//! without calculating it exactly.
+
+use crate::coordinate::{Accumulator, Coordinate, Distance};
+use crate::early_break::Witness;
+use crate::error::HausdorffError;
+use crate::input::{apply_non_finite_policy, check_shapes, retain_finite_rows};
+use crate::metric::Metric;
+use crate::options::{HausdorffOptions, NonFinitePolicy};
+use crate::parallel::{estimated_work, Batches, Executor};
+use crate::result::{Decision, Direction, HausdorffResult};
+use crate::shuffle::RowOrders;
+use ndarray::{ArrayView2, AsArray, Ix2};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Decide whether the directed Hausdorff distance from `ar1` to `ar2`
+/// is at most `epsilon`, as configured by [`HausdorffOptions`].
+///
+/// The search of a row stops at the first point of `ar2` within
+/// `epsilon`, and the whole search at the first row of `ar1` that has
+/// none, whose nearest neighbour is reported as the witness. Which of
+/// several such rows is found first depends on the order the rows are
+/// visited in (see [`Shuffle`](crate::Shuffle)) and, with more than
+/// one worker, on the timing of the workers, but the decision does not.
+///
+/// The witness of a distance beyond `epsilon` is only a lower bound on
+/// the directed distance. If non-finite values propagate, the check
+/// fails with the NaN result of [`try_directed_hausdorff_with`](crate::try_directed_hausdorff_with).
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0, 0.4], [1.0, 0.5], [2.0, 0.8], [3.0, 0.1]]);
+/// let a2 = arr2(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]);
+/// let options = HausdorffOptions::new();
+/// let decision = try_directed_hausdorff_within_with(&a1, &a2, 1.0, &options).unwrap();
+/// assert!(decision.is_within());
+/// match try_directed_hausdorff_within_with(&a1, &a2, 0.5, &options).unwrap() {
+///     Decision::Beyond { witness, .. } => assert_eq!(witness.ar1_index, 2),
+///     Decision::Within => unreachable!(),
+/// }
+/// ```
+pub fn try_directed_hausdorff_within_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    epsilon: Distance<T>,
+    options: &HausdorffOptions<M>,
+) -> Result<Decision<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    within_with(
+        ar1.into(),
+        ar2.into(),
+        epsilon,
+        false,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// Decide whether the (symmetric) Hausdorff distance between `ar1`
+/// and `ar2` is at most `epsilon`, as configured by [`HausdorffOptions`],
+/// e.g. whether two shapes match to within a manufacturing tolerance.
+///
+/// The forward direction is checked first, as by
+/// [`try_directed_hausdorff_within_with`], and the reverse direction
+/// only if every row of `ar1` is within `epsilon` of `ar2`.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0, 0.4], [1.0, 0.5], [2.0, 0.8]]);
+/// let a2 = arr2(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]);
+/// let options = HausdorffOptions::new();
+/// // (3, 0) is √2 from a1
+/// match try_hausdorff_within_with(&a1, &a2, 1.0, &options).unwrap() {
+///     Decision::Beyond { direction, witness } => {
+///         assert_eq!(direction, Direction::Reverse);
+///         assert_eq!(witness.ar1_index, 3);
+///     }
+///     Decision::Within => unreachable!(),
+/// }
+/// ```
+pub fn try_hausdorff_within_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    epsilon: Distance<T>,
+    options: &HausdorffOptions<M>,
+) -> Result<Decision<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    within_with(
+        ar1.into(),
+        ar2.into(),
+        epsilon,
+        true,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// The calculation behind the free functions and the corresponding
+/// [`HausdorffEngine`](crate::HausdorffEngine) methods, of the
+/// directed decision or, if `symmetric`, of the symmetric one.
+pub(crate) fn within_with<T: Coordinate, M: Metric<T>>(
+    ar1: ArrayView2<T>,
+    ar2: ArrayView2<T>,
+    epsilon: Distance<T>,
+    symmetric: bool,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+    orders: &mut RowOrders,
+) -> Result<Decision<T>, HausdorffError> {
+    check_shapes(&ar1, &ar2)?;
+    let metric = &options.metric;
+    metric.check_columns(ar1.ncols())?;
+    orders.prepare_forward(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    if symmetric {
+        orders.prepare_reverse(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    }
+    let RowOrders {
+        rows1,
+        inner_rows2,
+        rows2,
+        inner_rows1,
+        ..
+    } = orders;
+    let policy = options.non_finite_policy;
+    if let Some(witness) = apply_non_finite_policy(&ar1, &ar2, metric, policy, rows1, inner_rows2)?
+    {
+        return Ok(Decision::Beyond {
+            direction: Direction::Forward,
+            witness,
+        });
+    }
+    if symmetric && policy == NonFinitePolicy::Skip {
+        retain_finite_rows("ar2", &ar2, rows2)?;
+        retain_finite_rows("ar1", &ar1, inner_rows1)?;
+    }
+
+    let threshold = Threshold::new(metric, epsilon);
+    if let Some(witness) = find_beyond(
+        &ar1,
+        &ar2,
+        rows1,
+        inner_rows2,
+        &threshold,
+        options,
+        executor,
+    )? {
+        return Ok(Decision::Beyond {
+            direction: Direction::Forward,
+            witness,
+        });
+    }
+    if symmetric {
+        if let Some(witness) = find_beyond(
+            &ar2,
+            &ar1,
+            rows2,
+            inner_rows1,
+            &threshold,
+            options,
+            executor,
+        )? {
+            return Ok(Decision::Beyond {
+                direction: Direction::Reverse,
+                witness,
+            });
+        }
+    }
+    Ok(Decision::Within)
+}
+
+/// A row of `ar1` with no point of `ar2` within the `threshold`, among
+/// the `rows` and `inner_rows` of the arrays, searched by as many
+/// workers as the `options` allow.
+fn find_beyond<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    rows: &[usize],
+    inner_rows: &[usize],
+    threshold: &Threshold<T, M>,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+) -> Result<Option<HausdorffResult<T>>, HausdorffError> {
+    let metric = &options.metric;
+    let work = estimated_work(rows.len(), inner_rows.len(), ar1.ncols());
+    let available = executor.available_workers();
+    let workers = options.workers.resolve(rows.len(), work, available);
+    let batches = Batches::new(rows.len(), workers);
+    let beyond = AtomicBool::new(false);
+    let search = |_| {
+        let mut threshold = threshold.clone();
+        first_beyond(
+            ar1,
+            ar2,
+            metric,
+            rows,
+            inner_rows,
+            &batches,
+            &mut threshold,
+            &beyond,
+        )
+    };
+    let found = if workers <= 1 {
+        // single thread/serial approach
+        search(0)
+    } else {
+        // of the rows found before the workers stopped,
+        // the earliest in the visiting order
+        let found = executor.run_workers(workers, search)?;
+        found
+            .into_iter()
+            .flatten()
+            .min_by_key(|&(position, _)| position)
+    };
+    Ok(found.map(|(_, (d, i, j))| HausdorffResult::new(ar1, ar2, metric, d, i, j)))
+}
+
+/// The first of the `rows` of `ar1` in the batches this worker pulls
+/// from `batches` with no point of `ar2` within the `threshold`, with
+/// its position and the nearest neighbour found along the way. Finding
+/// one sets `beyond`, which stops every worker at its next row.
+#[allow(clippy::too_many_arguments)]
+fn first_beyond<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+    rows: &[usize],
+    inner_rows: &[usize],
+    batches: &Batches,
+    threshold: &mut Threshold<T, M>,
+    beyond: &AtomicBool,
+) -> Option<(usize, Witness<T::Accumulator>)> {
+    while let Some(batch) = batches.next_batch() {
+        'rows: for position in batch {
+            if beyond.load(Ordering::Relaxed) {
+                return None;
+            }
+            let i = rows[position];
+            let point = ar1.row(i);
+            let mut cmin = T::Accumulator::MAX;
+            let mut j_store = 0;
+            for &j in inner_rows {
+                let d = metric.reduced_distance(point, ar2.row(j));
+                if threshold.contains(d) {
+                    continue 'rows;
+                }
+                if d < cmin {
+                    cmin = d;
+                    j_store = j;
+                }
+            }
+            beyond.store(true, Ordering::Relaxed);
+            return Some((position, (cmin, i, j_store)));
+        }
+    }
+    None
+}
+
+/// Which reduced distances are within a distance `epsilon`, deciding
+/// by the [`Metric`] and remembering the largest reduced distance
+/// found within and the smallest beyond, so that the metric's final
+/// transform rarely has to be applied.
+pub(crate) struct Threshold<'m, T: Coordinate, M> {
+    metric: &'m M,
+    epsilon: Distance<T>,
+    within: Option<T::Accumulator>,
+    beyond: Option<T::Accumulator>,
+}
+
+impl<T: Coordinate, M> Clone for Threshold<'_, T, M> {
+    fn clone(&self) -> Self {
+        Threshold { ..*self }
+    }
+}
+
+impl<'m, T: Coordinate, M: Metric<T>> Threshold<'m, T, M> {
+    pub(crate) fn new(metric: &'m M, epsilon: Distance<T>) -> Self {
+        Threshold {
+            metric,
+            epsilon,
+            within: None,
+            beyond: None,
+        }
+    }
+
+    pub(crate) fn contains(&mut self, d: T::Accumulator) -> bool {
+        // the distance never decreases with the reduced distance
+        if self.within.is_some_and(|within| d <= within) {
+            return true;
+        }
+        if self.beyond.is_some_and(|beyond| d >= beyond) {
+            return false;
+        }
+        let contains = self.metric.distance(d) <= self.epsilon;
+        if contains {
+            self.within = Some(d);
+        } else {
+            self.beyond = Some(d);
+        }
+        contains
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures, try_directed_hausdorff_with, try_nearest_neighbours_with, Manhattan, Shuffle,
+    };
+    use ndarray::prelude::*;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn matches_exact_distances() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(24);
+        // integer coordinates, so that some distance is exactly the tolerance
+        let a1 = Array2::from_shape_fn((300, 3), |_| rng.gen_range(0_i32..40));
+        let a2 = Array2::from_shape_fn((100, 3), |_| rng.gen_range(0_i32..40));
+        let check = |options: HausdorffOptions<Manhattan>| {
+            let exact = try_directed_hausdorff_with(&a1, &a2, &options)
+                .unwrap()
+                .distance;
+            let reverse = try_directed_hausdorff_with(&a2, &a1, &options)
+                .unwrap()
+                .distance;
+            let (nearest, _) = try_nearest_neighbours_with(&a1, &a2, &options).unwrap();
+            for epsilon in [0.0, exact - 1.0, exact, exact + 0.5, reverse] {
+                let decision = try_directed_hausdorff_within_with(&a1, &a2, epsilon, &options);
+                match decision.unwrap() {
+                    Decision::Within => assert!(exact <= epsilon),
+                    Decision::Beyond { direction, witness } => {
+                        assert_eq!(direction, Direction::Forward);
+                        assert!(witness.distance > epsilon);
+                        assert_eq!(witness.distance, nearest[[witness.ar1_index]]);
+                    }
+                }
+                let decision = try_hausdorff_within_with(&a1, &a2, epsilon, &options);
+                match decision.unwrap() {
+                    Decision::Within => assert!(exact.max(reverse) <= epsilon),
+                    Decision::Beyond { direction, witness } => {
+                        let expected = if exact > epsilon {
+                            Direction::Forward
+                        } else {
+                            Direction::Reverse
+                        };
+                        assert_eq!(direction, expected);
+                        assert!(witness.distance > epsilon);
+                    }
+                }
+            }
+        };
+        for workers in [1, 2, 5] {
+            for shuffle in [Shuffle::default(), Shuffle::SciPy(6), Shuffle::Disabled] {
+                let options = HausdorffOptions::new()
+                    .metric(Manhattan)
+                    .workers(workers)
+                    .shuffle(shuffle);
+                check(options);
+            }
+        }
+    }
+
+    #[test]
+    fn scipy_reference_values() {
+        // the SciPy distances are within, and anything less is beyond
+        let (path_1, path_2) = fixtures::paths();
+        let (forward, reverse) = (fixtures::PATHS_FORWARD, fixtures::PATHS_REVERSE);
+        for workers in [1, 4] {
+            let options = HausdorffOptions::new().workers(workers);
+            let within = |epsilon| {
+                try_directed_hausdorff_within_with(&path_1, &path_2, epsilon, &options).unwrap()
+            };
+            assert!(within(forward).is_within());
+            assert!(!within(1.0006815243614).is_within());
+            let within = |epsilon| {
+                let decision = try_hausdorff_within_with(&path_1, &path_2, epsilon, &options);
+                decision.unwrap()
+            };
+            assert!(within(reverse).is_within());
+            match within(2.3) {
+                Decision::Beyond { direction, witness } => {
+                    assert_eq!(direction, Direction::Reverse);
+                    assert_eq!(witness.distance, reverse);
+                }
+                Decision::Within => panic!("2.3 is below the SciPy distance"),
+            }
+            match within(1.0) {
+                Decision::Beyond { direction, .. } => assert_eq!(direction, Direction::Forward),
+                Decision::Within => panic!("1.0 is below the SciPy distance"),
+            }
+        }
+    }
+
+    #[test]
+    fn non_finite_policies() {
+        let a1 = arr2(&[[0.0, 0.0], [f64::NAN, 1.0], [3.0, 4.0]]);
+        let a2 = arr2(&[[0.0, 0.0]]);
+        let options = HausdorffOptions::new();
+        assert!(try_hausdorff_within_with(&a1, &a2, 5.0, &options).is_err());
+        let options = options.non_finite_policy(NonFinitePolicy::Skip);
+        let decision = try_hausdorff_within_with(&a1, &a2, 5.0, &options);
+        assert_eq!(decision, Ok(Decision::Within));
+        let options = options.non_finite_policy(NonFinitePolicy::Propagate);
+        match try_hausdorff_within_with(&a1, &a2, 5.0, &options).unwrap() {
+            Decision::Beyond { witness, .. } => assert!(witness.distance.is_nan()),
+            Decision::Within => panic!("NaN distances are never within a tolerance"),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! The serial "early break" search over batches of rows.
+
+use crate::coordinate::{Accumulator, Coordinate};
+use crate::metric::Metric;
+use crate::parallel::Batches;
+use ndarray::ArrayView2;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A reduced distance, with the row of the outer array
+/// realising it and the row of its nearest neighbour.
+pub(crate) type Witness<A> = (A, usize, usize);
+
+/// A running maximum reduced distance, shared between the workers
+/// so that each may break early against the largest distance found
+/// by any of them rather than only its own.
+///
+/// Any value loaded is a distance that some row really realises, so
+/// it never exceeds the final maximum and the rows realising that
+/// maximum are still found (and tie-broken) exactly as without it.
+pub(crate) struct SharedBound<A> {
+    bits: AtomicU64,
+    accumulator: PhantomData<A>,
+}
+
+impl<A: Accumulator> SharedBound<A> {
+    pub(crate) fn new(bound: A) -> Self {
+        SharedBound {
+            bits: AtomicU64::new(bound.to_bound_bits()),
+            accumulator: PhantomData,
+        }
+    }
+
+    pub(crate) fn load(&self) -> A {
+        // a stale value is merely a looser bound,
+        // so no ordering with other memory is needed
+        A::from_bound_bits(self.bits.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn raise(&self, bound: A) {
+        self.bits
+            .fetch_max(bound.to_bound_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Search the `rows` of `ar1`, in order, for the one farthest from
+/// the `inner_rows` of `ar2` by the `metric`, only recording rows at least `bound`
+/// away. With a `shared` bound, rows are also only recorded if they
+/// are at least as far as the largest distance published by the
+/// other workers. Returns `None` if no row was recorded.
+pub(crate) fn directed_hausdorff_core<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+    rows: &[usize],
+    inner_rows: &[usize],
+    bound: T::Accumulator,
+    shared: Option<&SharedBound<T::Accumulator>>,
+) -> Option<Witness<T::Accumulator>> {
+    let mut cmax = bound;
+    let mut d = T::Accumulator::ZERO;
+    let mut i_store = 0;
+    let mut j_store = 0;
+    let mut found = None;
+
+    for &i in rows {
+        let mut cmin = T::Accumulator::MAX;
+        let point = ar1.row(i);
+        for j in inner_rows {
+            // reduced (i.e., squared) distance -- avoid
+            // sqrt until very end for performance
+            d = metric.reduced_distance(point, ar2.row(*j));
+            if d < cmax {
+                break;
+            }
+            if let Some(shared) = shared {
+                let global = shared.load();
+                if global > cmax {
+                    cmax = global;
+                    if d < cmax {
+                        break;
+                    }
+                }
+            }
+            if d < cmin {
+                cmin = d;
+                i_store = i;
+                j_store = *j;
+            }
+        }
+        // Note: The reference paper by A. A. Taha and A. Hanbury has this line
+        // (Algorithm 2, line 16) as:
+        //
+        // if cmin > cmax:
+        //
+        // That logic is incorrect, as cmin could still be np.inf if breaking early.
+        // The logic here accounts for that case.
+        if cmin >= cmax && d >= cmax {
+            cmax = cmin;
+            found = Some((cmin, i_store, j_store));
+            if let Some(shared) = shared {
+                shared.raise(cmax);
+            }
+        }
+    }
+    found
+}
+
+/// Search batches of the `rows` of `ar1`, pulled from `batches` until
+/// none remain, carrying the running maximum from batch to batch.
+/// Returns the farthest row recorded along with the start of its batch,
+/// by which [`merge`] breaks ties.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn directed_hausdorff_batches<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+    rows: &[usize],
+    inner_rows: &[usize],
+    batches: &Batches,
+    bound: T::Accumulator,
+    shared: Option<&SharedBound<T::Accumulator>>,
+) -> Option<(Witness<T::Accumulator>, usize)> {
+    let mut cmax = bound;
+    let mut found = None;
+    while let Some(batch) = batches.next_batch() {
+        let start = batch.start;
+        // anything recorded is at least as far as the
+        // rows of earlier batches, and comes later
+        if let Some(witness) =
+            directed_hausdorff_core(ar1, ar2, metric, &rows[batch], inner_rows, cmax, shared)
+        {
+            cmax = witness.0;
+            found = Some((witness, start));
+        }
+    }
+    found
+}
+
+/// Combine the witnesses found by the workers. Like the serial
+/// loop, the latest row is preferred in case of ties, so the result
+/// does not depend on how the batches were divided between workers.
+pub(crate) fn merge<A: PartialOrd>(
+    results: impl IntoIterator<Item = Option<(Witness<A>, usize)>>,
+) -> Option<Witness<A>> {
+    results
+        .into_iter()
+        .flatten()
+        .reduce(|best, result| {
+            let (value, start) = (&result.0 .0, result.1);
+            if *value > best.0 .0 || (*value >= best.0 .0 && start > best.1) {
+                result
+            } else {
+                best
+            }
+        })
+        .map(|(witness, _)| witness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_bound_only_rises() {
+        let bound = SharedBound::new(2.5_f64);
+        bound.raise(1.0);
+        assert_eq!(bound.load(), 2.5);
+        bound.raise(6.25);
+        assert_eq!(bound.load(), 6.25);
+        let bound = SharedBound::new(0.0_f32);
+        bound.raise(f32::MIN_POSITIVE);
+        assert_eq!(bound.load(), f32::MIN_POSITIVE);
+        // squared distances beyond u64 saturate
+        // to a smaller, but still valid, bound
+        let bound = SharedBound::new(0_u128);
+        bound.raise(u128::MAX);
+        assert_eq!(bound.load(), u64::MAX as u128);
+        bound.raise(7);
+        assert_eq!(bound.load(), u64::MAX as u128);
+    }
+}
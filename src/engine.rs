@@ -0,0 +1,610 @@
+//! A reusable engine for calculating many Hausdorff distances.
+
+use crate::average::{average_with, Averaging};
+use crate::chamfer::{chamfer_with, Chamfer};
+use crate::coordinate::{Coordinate, Distance};
+use crate::cutoff::{bounded_with, into_directed};
+use crate::decision::within_with;
+use crate::error::HausdorffError;
+use crate::histogram::{distribution_with, Histogram};
+use crate::metric::{Euclidean, Metric};
+use crate::nearest::{nearest_with, outliers, scatter_rows};
+use crate::options::HausdorffOptions;
+use crate::parallel::{Backend, Executor, WorkerPool};
+use crate::partial::{farthest_with, partial_with, Partial};
+use crate::result::{
+    Bounded, Decision, DistanceDistribution, HausdorffResult, Outliers, SymmetricHausdorffResult,
+};
+use crate::shuffle::RowOrders;
+use crate::symmetric::symmetric_with;
+use ndarray::{Array1, AsArray, Ix2};
+
+/// Calculates Hausdorff distances with a fixed set of
+/// [`HausdorffOptions`], keeping its worker threads and
+/// scratch buffers alive between calculations.
+///
+/// The free functions spawn their worker threads and shuffle the
+/// rows afresh for every call, which dominates the running time for
+/// small inputs. An engine spawns its threads once, when it is
+/// created, and reuses the shuffled row orders for as long as the
+/// arrays keep the same numbers of rows. The results are identical
+/// to those of the free functions with the same options.
+///
+/// With one of the rayon [`Backend`]s, the work runs on the rayon
+/// pool instead and the engine owns no threads of its own.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let mut engine = HausdorffEngine::new(HausdorffOptions::new().workers(2));
+/// let a1 = arr2(&[[1.0, 0.0], [0.0, 1.0], [-1.0, 0.0], [0.0, -1.0]]);
+/// for offset in 0..10 {
+///     let a2 = arr2(&[[2.0, 0.0], [0.0, 2.0], [-2.0, 0.0], [0.0, -4.0]]) + offset as f64;
+///     let result = engine.directed(&a1, &a2).unwrap();
+///     assert_eq!(result, try_directed_hausdorff_with(&a1, &a2, engine.options()).unwrap());
+/// }
+/// let result = engine.symmetric(&a1, &a1).unwrap();
+/// assert_eq!(result.distance, 0.0);
+/// ```
+pub struct HausdorffEngine<M = Euclidean> {
+    options: HausdorffOptions<M>,
+    pool: Option<WorkerPool>,
+    orders: RowOrders,
+}
+
+impl<M> HausdorffEngine<M> {
+    /// Create an engine, spawning the worker
+    /// threads requested by the `options`.
+    pub fn new(options: HausdorffOptions<M>) -> Self {
+        // enough threads for the largest calculations
+        let available = options.backend.available_workers();
+        let workers = options.workers.resolve(usize::MAX, u64::MAX, available);
+        let pool = match options.backend {
+            Backend::Threads if workers > 1 => Some(WorkerPool::new(workers)),
+            _ => None,
+        };
+        HausdorffEngine {
+            options,
+            pool,
+            orders: RowOrders::default(),
+        }
+    }
+
+    /// The options the engine was created with.
+    pub fn options(&self) -> &HausdorffOptions<M> {
+        &self.options
+    }
+
+    /// Calculate the directed Hausdorff distance from `ar1` to `ar2`,
+    /// as [`try_directed_hausdorff_with`](crate::try_directed_hausdorff_with) would.
+    pub fn directed<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+    ) -> Result<HausdorffResult<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        crate::directed_with(
+            ar1.into(),
+            ar2.into(),
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Calculate the (symmetric) Hausdorff distance between `ar1`
+    /// and `ar2`, as [`try_hausdorff_with`](crate::try_hausdorff_with) would.
+    pub fn symmetric<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+    ) -> Result<SymmetricHausdorffResult<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        symmetric_with(
+            ar1.into(),
+            ar2.into(),
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Calculate the partial Hausdorff distance from `ar1` to `ar2`, as
+    /// [`try_directed_partial_hausdorff_with`](crate::try_directed_partial_hausdorff_with)
+    /// would.
+    pub fn partial<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        partial: Partial,
+    ) -> Result<HausdorffResult<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        partial_with(
+            ar1.into(),
+            ar2.into(),
+            partial,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Find the `k` rows of `ar1` farthest from `ar2`, as
+    /// [`try_farthest_points_with`](crate::try_farthest_points_with) would.
+    pub fn farthest_points<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        k: usize,
+    ) -> Result<Vec<HausdorffResult<T>>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        farthest_with(
+            ar1.into(),
+            ar2.into(),
+            k,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Calculate the directed average Hausdorff distance from `ar1` to `ar2`, as
+    /// [`try_directed_average_hausdorff_with`](crate::try_directed_average_hausdorff_with)
+    /// would.
+    pub fn directed_average<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        averaging: Averaging,
+    ) -> Result<Distance<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        average_with(
+            ar1.into(),
+            ar2.into(),
+            averaging,
+            false,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Calculate the (symmetric) average Hausdorff distance between `ar1` and `ar2`,
+    /// as [`try_average_hausdorff_with`](crate::try_average_hausdorff_with) would.
+    pub fn average<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        averaging: Averaging,
+    ) -> Result<Distance<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        average_with(
+            ar1.into(),
+            ar2.into(),
+            averaging,
+            true,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Calculate the directed Chamfer distance from `ar1` to `ar2`, as
+    /// [`try_directed_chamfer_distance_with`](crate::try_directed_chamfer_distance_with)
+    /// would.
+    pub fn directed_chamfer<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        chamfer: Chamfer,
+    ) -> Result<Distance<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        chamfer_with(
+            ar1.into(),
+            ar2.into(),
+            chamfer,
+            false,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Calculate the (bidirectional) Chamfer distance between `ar1` and `ar2`,
+    /// as [`try_chamfer_distance_with`](crate::try_chamfer_distance_with) would.
+    pub fn chamfer<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        chamfer: Chamfer,
+    ) -> Result<Distance<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        chamfer_with(
+            ar1.into(),
+            ar2.into(),
+            chamfer,
+            true,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Find the nearest neighbour in `ar2` of every row of `ar1`, as
+    /// [`try_nearest_neighbours_with`](crate::try_nearest_neighbours_with) would.
+    pub fn nearest_neighbours<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+    ) -> Result<(Array1<Distance<T>>, Array1<usize>), HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        let ar1 = ar1.into();
+        let orders = &mut self.orders;
+        let nearest = nearest_with(ar1, ar2.into(), None, &self.options, executor, orders)?;
+        Ok(scatter_rows(ar1.nrows(), nearest))
+    }
+
+    /// Find the rows of `ar1` that are farther than `tolerance` from every row of `ar2`, as
+    /// [`try_nearest_neighbours_beyond_with`](crate::try_nearest_neighbours_beyond_with)
+    /// would.
+    pub fn nearest_neighbours_beyond<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        tolerance: Distance<T>,
+    ) -> Result<Outliers<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        let nearest = nearest_with(
+            ar1.into(),
+            ar2.into(),
+            Some(tolerance),
+            &self.options,
+            executor,
+            &mut self.orders,
+        )?;
+        Ok(outliers(nearest))
+    }
+
+    /// Calculate the distribution of the nearest neighbour distances from `ar1` to `ar2`, as
+    /// [`try_directed_distance_distribution_with`](crate::try_directed_distance_distribution_with)
+    /// would.
+    pub fn distance_distribution<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        histogram: &Histogram,
+    ) -> Result<DistanceDistribution, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        distribution_with(
+            ar1.into(),
+            ar2.into(),
+            histogram,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Decide whether the directed Hausdorff distance from `ar1` to `ar2` is at most `epsilon`, as
+    /// [`try_directed_hausdorff_within_with`](crate::try_directed_hausdorff_within_with)
+    /// would.
+    pub fn directed_within<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        epsilon: Distance<T>,
+    ) -> Result<Decision<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        within_with(
+            ar1.into(),
+            ar2.into(),
+            epsilon,
+            false,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Decide whether the (symmetric) Hausdorff distance between `ar1` and `ar2` is at most
+    /// `epsilon`, as [`try_hausdorff_within_with`](crate::try_hausdorff_within_with) would.
+    pub fn within<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        epsilon: Distance<T>,
+    ) -> Result<Decision<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        within_with(
+            ar1.into(),
+            ar2.into(),
+            epsilon,
+            true,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+
+    /// Calculate the directed Hausdorff distance from `ar1` to `ar2` up to `max_distance`, as
+    /// [`try_directed_hausdorff_bounded_with`](crate::try_directed_hausdorff_bounded_with)
+    /// would.
+    pub fn directed_bounded<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        max_distance: Distance<T>,
+    ) -> Result<Bounded<T>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        let bounded = bounded_with(
+            ar1.into(),
+            ar2.into(),
+            max_distance,
+            false,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )?;
+        Ok(into_directed(bounded))
+    }
+
+    /// Calculate the (symmetric) Hausdorff distance between `ar1` and `ar2` up to
+    /// `max_distance`, as [`try_hausdorff_bounded_with`](crate::try_hausdorff_bounded_with)
+    /// would.
+    pub fn bounded<'a, 'b, T, V1, V2>(
+        &mut self,
+        ar1: V1,
+        ar2: V2,
+        max_distance: Distance<T>,
+    ) -> Result<Bounded<T, SymmetricHausdorffResult<T>>, HausdorffError>
+    where
+        T: Coordinate + 'a + 'b,
+        M: Metric<T>,
+        V1: AsArray<'a, T, Ix2>,
+        V2: AsArray<'b, T, Ix2>,
+    {
+        let executor = executor(&self.options, &self.pool);
+        bounded_with(
+            ar1.into(),
+            ar2.into(),
+            max_distance,
+            true,
+            &self.options,
+            executor,
+            &mut self.orders,
+        )
+    }
+}
+
+fn executor<'e, M>(options: &'e HausdorffOptions<M>, pool: &'e Option<WorkerPool>) -> Executor<'e> {
+    match pool {
+        Some(pool) => Executor::Pool(pool),
+        None => Executor::Backend(&options.backend),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{try_directed_hausdorff_with, try_hausdorff_with, NonFinitePolicy, Shuffle};
+    use ndarray::prelude::*;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn matches_free_functions() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(3);
+        for workers in [1, 2, 5] {
+            for shuffle in [Shuffle::default(), Shuffle::SciPy(0), Shuffle::Disabled] {
+                let options = HausdorffOptions::new()
+                    .workers(workers)
+                    .shuffle(shuffle)
+                    .non_finite_policy(NonFinitePolicy::Skip);
+                let mut engine = HausdorffEngine::new(options.clone());
+                // the row orders are reused while the sizes repeat
+                for rows in [10, 10, 3, 40, 40, 10] {
+                    let mut a1 = Array2::from_shape_fn((rows, 3), |_| rng.gen_range(0.0..1.0));
+                    let a2 = Array2::from_shape_fn((rows / 2 + 1, 3), |_| rng.gen_range(0.0..1.0));
+                    a1[[rows / 2, 1]] = f64::NAN;
+                    assert_eq!(
+                        engine.directed(&a1, &a2),
+                        try_directed_hausdorff_with(&a1, &a2, &options)
+                    );
+                    assert_eq!(
+                        engine.directed(&a2, &a1),
+                        try_directed_hausdorff_with(&a2, &a1, &options)
+                    );
+                    assert_eq!(
+                        engine.symmetric(&a1, &a2),
+                        try_hausdorff_with(&a1, &a2, &options)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn all_methods_match_free_functions() {
+        use crate::*;
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(4);
+        let histogram = Histogram::uniform(0.0, 0.5, 5).quantiles([0.5, 0.95]);
+        for workers in [1, 2, 5] {
+            for shuffle in [Shuffle::default(), Shuffle::SciPy(0), Shuffle::Disabled] {
+                let options = HausdorffOptions::new()
+                    .workers(workers)
+                    .shuffle(shuffle)
+                    .non_finite_policy(NonFinitePolicy::Skip);
+                let mut engine = HausdorffEngine::new(options.clone());
+                for rows in [10, 10, 3, 40, 40, 10] {
+                    let mut a1 = Array2::from_shape_fn((rows, 3), |_| rng.gen_range(0.0..1.0));
+                    let a2 = Array2::from_shape_fn((rows / 2 + 1, 3), |_| rng.gen_range(0.0..1.0));
+                    a1[[rows / 2, 1]] = f64::NAN;
+                    let partial = Partial::quantile(0.9);
+                    assert_eq!(
+                        engine.partial(&a1, &a2, partial),
+                        try_directed_partial_hausdorff_with(&a1, &a2, partial, &options)
+                    );
+                    assert_eq!(
+                        engine.farthest_points(&a1, &a2, 3),
+                        try_farthest_points_with(&a1, &a2, 3, &options)
+                    );
+                    let averaging = Averaging::modified();
+                    assert_eq!(
+                        engine.directed_average(&a1, &a2, averaging),
+                        try_directed_average_hausdorff_with(&a1, &a2, averaging, &options)
+                    );
+                    assert_eq!(
+                        engine.average(&a1, &a2, averaging),
+                        try_average_hausdorff_with(&a1, &a2, averaging, &options)
+                    );
+                    let chamfer = Chamfer::mean().squared(true);
+                    assert_eq!(
+                        engine.directed_chamfer(&a1, &a2, chamfer),
+                        try_directed_chamfer_distance_with(&a1, &a2, chamfer, &options)
+                    );
+                    assert_eq!(
+                        engine.chamfer(&a1, &a2, chamfer),
+                        try_chamfer_distance_with(&a1, &a2, chamfer, &options)
+                    );
+                    // the skipped rows have NaN distances
+                    assert_eq!(
+                        format!("{:?}", engine.nearest_neighbours(&a1, &a2)),
+                        format!("{:?}", try_nearest_neighbours_with(&a1, &a2, &options))
+                    );
+                    assert_eq!(
+                        engine.nearest_neighbours_beyond(&a1, &a2, 0.3),
+                        try_nearest_neighbours_beyond_with(&a1, &a2, 0.3, &options)
+                    );
+                    assert_eq!(
+                        engine.distance_distribution(&a1, &a2, &histogram),
+                        try_directed_distance_distribution_with(&a1, &a2, &histogram, &options)
+                    );
+                    // in parallel, the witness beyond the tolerance or
+                    // the cutoff depends on the timing of the workers
+                    for tolerance in [0.3, 0.6] {
+                        let actual = engine.directed_within(&a1, &a2, tolerance).unwrap();
+                        let expected =
+                            try_directed_hausdorff_within_with(&a1, &a2, tolerance, &options);
+                        let expected = expected.unwrap();
+                        assert_eq!(actual.is_within(), expected.is_within());
+                        let actual = engine.within(&a1, &a2, tolerance).unwrap();
+                        let expected = try_hausdorff_within_with(&a1, &a2, tolerance, &options);
+                        assert!(workers > 1 || Ok(&actual) == expected.as_ref());
+                        assert_eq!(actual.is_within(), expected.unwrap().is_within());
+
+                        let actual = engine.directed_bounded(&a1, &a2, tolerance).unwrap();
+                        let expected =
+                            try_directed_hausdorff_bounded_with(&a1, &a2, tolerance, &options);
+                        let expected = expected.unwrap();
+                        assert_eq!(actual.is_exact(), expected.is_exact());
+                        let actual = engine.bounded(&a1, &a2, tolerance).unwrap();
+                        let expected = try_hausdorff_bounded_with(&a1, &a2, tolerance, &options);
+                        assert!(workers > 1 || Ok(&actual) == expected.as_ref());
+                        assert_eq!(actual.is_exact(), expected.unwrap().is_exact());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn errors_leave_the_engine_usable() {
+        let mut engine = HausdorffEngine::new(HausdorffOptions::new().workers(3));
+        let a1 = arr2(&[[0_u8, 0], [3, 4]]);
+        let a2 = arr2(&[[0_u8, 0, 0]]);
+        assert!(engine.directed(&a1, &a2).is_err());
+        assert!(engine.symmetric(&a1, a1.slice(s![..0, ..])).is_err());
+        assert_eq!(
+            engine
+                .directed(&a1, a1.slice(s![..1, ..]))
+                .unwrap()
+                .distance,
+            5.0
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Errors reported by the fallible Hausdorff distance entry points.
+
+use std::error::Error;
+use std::fmt;
+
+/// The reasons a Hausdorff distance calculation may fail.
+///
+/// The `argument` fields name the offending input, i.e.,
+/// `"ar1"` or `"ar2"`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HausdorffError {
+    /// The two arrays of points have different numbers of columns.
+    DimensionMismatch {
+        ar1_columns: usize,
+        ar2_columns: usize,
+    },
+    /// An array contains no points, so the distance is undefined.
+    EmptyInput { argument: &'static str },
+    /// An array contains a NaN or infinite coordinate.
+    NonFinite {
+        argument: &'static str,
+        row: usize,
+        column: usize,
+    },
+    /// A caller provided row ordering is not a
+    /// permutation of the `rows` rows of the array.
+    InvalidPermutation { argument: &'static str, rows: usize },
+    /// A worker thread panicked, with the panic message if
+    /// one was available.
+    WorkerFailed(String),
+    /// The [`Metric`](crate::Metric) does not apply to the
+    /// points, with the reason why.
+    InvalidMetric(String),
+    /// The [`Partial`](crate::Partial) rank, or a [`Histogram`](crate::Histogram)
+    /// quantile, does not exist, with the reason why.
+    InvalidRank(String),
+}
+
+impl fmt::Display for HausdorffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HausdorffError::DimensionMismatch {
+                ar1_columns,
+                ar2_columns,
+            } => write!(
+                f,
+                "points must have the same dimension, but ar1 has {} columns and ar2 has {}",
+                ar1_columns, ar2_columns
+            ),
+            HausdorffError::EmptyInput { argument } => {
+                write!(f, "{} must contain at least one point", argument)
+            }
+            HausdorffError::NonFinite {
+                argument,
+                row,
+                column,
+            } => write!(f, "{}[[{}, {}]] is not finite", argument, row, column),
+            HausdorffError::InvalidPermutation { argument, rows } => write!(
+                f,
+                "the {} ordering is not a permutation of 0..{}",
+                argument, rows
+            ),
+            HausdorffError::WorkerFailed(message) => {
+                write!(f, "a worker thread failed: {}", message)
+            }
+            HausdorffError::InvalidMetric(message) => write!(f, "invalid metric: {}", message),
+            HausdorffError::InvalidRank(message) => write!(f, "invalid rank: {}", message),
+        }
+    }
+}
+
+impl Error for HausdorffError {}
+
+impl HausdorffError {
+    /// Recover the message from the payload of a panicked thread.
+    pub(crate) fn from_panic(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic payload".to_string()
+        };
+        HausdorffError::WorkerFailed(message)
+    }
+}
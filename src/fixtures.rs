@@ -0,0 +1,34 @@
+//! The arrays of SciPy's `test_hausdorff.py`, shared by the tests
+//! that compare against its reference values.
+
+use ndarray::prelude::*;
+
+/// The arrays of `TestHausdorff::test_indices`, `(a1, a2)`.
+pub(crate) fn indices() -> (Array2<f64>, Array2<f64>) {
+    let a1 = arr2(&[[-1., -12.], [0., 0.], [1., 1.], [3., 7.], [1., 2.]]);
+    let a2 = arr2(&[[0., 0.], [1., 1.], [4., 100.], [10., 9.]]);
+    (a1, a2)
+}
+
+/// SciPy's `directed_hausdorff(a2, a1)` of the arrays of [`indices`],
+/// where the distance is √8650.
+pub(crate) const INDICES_REVERSE: (f64, usize, usize) = (93.00537618869137, 2, 3);
+
+/// An array of `src/paths.npz`, e.g., `"path_1"`.
+pub(crate) fn path(name: &str) -> Array2<f64> {
+    let file = std::fs::File::open("src/paths.npz").expect("unable to open data file");
+    let mut npz = ndarray_npy::NpzReader::new(file).expect("unable to read from file");
+    npz.by_name(&format!("{}.npy", name))
+        .unwrap_or_else(|_| panic!("unable to retrieve {} field", name))
+}
+
+/// The paths of `TestHausdorff::test_symmetry`, `(path_1, path_2)`.
+pub(crate) fn paths() -> (Array2<f64>, Array2<f64>) {
+    (path("path_1"), path("path_2"))
+}
+
+/// SciPy's `directed_hausdorff(path_1, path_2)` of the [`paths`].
+pub(crate) const PATHS_FORWARD: f64 = 1.000681524361451;
+
+/// SciPy's `directed_hausdorff(path_2, path_1)` of the [`paths`].
+pub(crate) const PATHS_REVERSE: f64 = 2.3000000000000003;
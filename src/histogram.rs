@@ -0,0 +1,362 @@
+//! The distribution of the nearest neighbour distances, as a
+//! histogram and summary statistics.
+
+use crate::coordinate::{Accumulator, Coordinate};
+use crate::error::HausdorffError;
+use crate::input::{apply_non_finite_policy, check_shapes};
+use crate::metric::Metric;
+use crate::nearest::nearest_neighbour;
+use crate::options::HausdorffOptions;
+use crate::parallel::{estimated_work, Batches, Executor};
+use crate::partial::nearest_rank;
+use crate::result::DistanceDistribution;
+use crate::shuffle::RowOrders;
+use ndarray::{Array1, ArrayView2, AsArray, Ix2};
+use num_traits::ToPrimitive;
+
+/// The bins of a [`DistanceDistribution`], and the quantiles
+/// of the distances to report along with it.
+///
+/// The bins are half open, `[edges[b], edges[b + 1])`, but for the
+/// last, which also holds the distances equal to its upper edge,
+/// as for `numpy.histogram`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    edges: Vec<f64>,
+    quantiles: Vec<f64>,
+}
+
+impl Histogram {
+    /// Bins between consecutive `edges`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than two edges, or if they
+    /// are not finite and strictly increasing.
+    pub fn new(edges: impl Into<Vec<f64>>) -> Self {
+        let edges = edges.into();
+        assert!(edges.len() >= 2, "a histogram needs at least two edges");
+        assert!(
+            edges.iter().all(|e| e.is_finite()) && edges.windows(2).all(|e| e[0] < e[1]),
+            "the histogram edges must be finite and strictly increasing"
+        );
+        Histogram {
+            edges,
+            quantiles: Vec::new(),
+        }
+    }
+
+    /// `bins` bins of equal width from `start` to `end`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bins` is zero, or if `start` and `end` are not
+    /// finite with `start < end`.
+    pub fn uniform(start: f64, end: f64, bins: usize) -> Self {
+        let width = (end - start) / bins as f64;
+        let edges: Vec<_> = (0..=bins).map(|b| start + width * b as f64).collect();
+        Histogram::new(edges)
+    }
+
+    /// The quantiles, each between 0 and 1, of the distances to report,
+    /// by the nearest rank definition of [`Partial::quantile`](crate::Partial::quantile),
+    /// e.g. `[0.05, 0.95]`. None by default.
+    pub fn quantiles(mut self, quantiles: impl Into<Vec<f64>>) -> Self {
+        self.quantiles = quantiles.into();
+        self
+    }
+
+    /// The position of `distance` among the counts, with one more
+    /// count below the first bin and one above the last.
+    fn position(&self, distance: f64) -> usize {
+        let position = self.edges.partition_point(|&e| e <= distance);
+        let bins = self.edges.len() - 1;
+        if position == bins + 1 && distance == self.edges[bins] {
+            bins
+        } else {
+            position
+        }
+    }
+}
+
+/// Calculate the distribution of the distances from the rows of `ar1`
+/// to their nearest neighbours in `ar2`, binned by the [`Histogram`]
+/// and configured by [`HausdorffOptions`].
+///
+/// The directed Hausdorff distance is the `max` of the distribution.
+/// Every point needs its exact nearest neighbour, so there are no
+/// early breaks, but the rows are distributed over the workers in
+/// batches in the same way. Every worker bins the distances it finds
+/// in a histogram of its own, and the histograms are summed at the
+/// end. The statistics are calculated in the order of the rows, so
+/// that none of the results depend on the number of workers or on the
+/// [`Shuffle`](crate::Shuffle).
+///
+/// Rows skipped by the [`NonFinitePolicy`](crate::NonFinitePolicy)
+/// are not counted. If non-finite values propagate, the statistics
+/// are NaN and no distance is counted.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0, 1.0], [1.0, 2.0], [2.0, 3.0], [3.0, 1.0]]);
+/// let a2 = arr2(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]);
+/// let options = HausdorffOptions::new();
+/// let histogram = Histogram::uniform(0.0, 2.0, 2).quantiles([0.25]);
+/// let distribution =
+///     try_directed_distance_distribution_with(&a1, &a2, &histogram, &options).unwrap();
+/// assert_eq!(distribution.edges, arr1(&[0.0, 1.0, 2.0]));
+/// assert_eq!(distribution.counts, arr1(&[0, 3]));
+/// assert_eq!(distribution.above, 1);
+/// assert_eq!(distribution.cumulative_counts(), arr1(&[0, 3]));
+/// assert_eq!(distribution.mean, 1.75);
+/// assert_eq!(distribution.rms, 3.75_f64.sqrt());
+/// assert_eq!(distribution.median, 1.0);
+/// assert_eq!(distribution.max, 3.0);
+/// assert_eq!(distribution.quantiles, arr1(&[1.0]));
+/// ```
+pub fn try_directed_distance_distribution_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    histogram: &Histogram,
+    options: &HausdorffOptions<M>,
+) -> Result<DistanceDistribution, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    distribution_with(
+        ar1.into(),
+        ar2.into(),
+        histogram,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// The calculation behind both [`try_directed_distance_distribution_with`] and
+/// [`HausdorffEngine::distance_distribution`](crate::HausdorffEngine::distance_distribution).
+pub(crate) fn distribution_with<T: Coordinate, M: Metric<T>>(
+    ar1: ArrayView2<T>,
+    ar2: ArrayView2<T>,
+    histogram: &Histogram,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+    orders: &mut RowOrders,
+) -> Result<DistanceDistribution, HausdorffError> {
+    check_shapes(&ar1, &ar2)?;
+    let metric = &options.metric;
+    metric.check_columns(ar1.ncols())?;
+    orders.prepare_forward(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    let RowOrders {
+        rows1, inner_rows2, ..
+    } = orders;
+    let policy = options.non_finite_policy;
+    let propagated = apply_non_finite_policy(&ar1, &ar2, metric, policy, rows1, inner_rows2)?;
+    // the quantiles are checked against the rows that remain
+    let ranks = histogram
+        .quantiles
+        .iter()
+        .map(|&q| nearest_rank(q, rows1.len()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let bins = histogram.edges.len() - 1;
+    if propagated.is_some() {
+        return Ok(DistanceDistribution {
+            edges: histogram.edges.clone().into(),
+            counts: Array1::zeros(bins),
+            below: 0,
+            above: 0,
+            mean: f64::NAN,
+            rms: f64::NAN,
+            median: f64::NAN,
+            max: f64::NAN,
+            quantiles: Array1::from_elem(ranks.len(), f64::NAN),
+        });
+    }
+    let (rows, inner_rows) = (&rows1[..], &inner_rows2[..]);
+
+    let work = estimated_work(rows.len(), inner_rows.len(), ar1.ncols());
+    let available = executor.available_workers();
+    let workers = options.workers.resolve(rows.len(), work, available);
+    let batches = Batches::new(rows.len(), workers);
+    let search = |_| {
+        let mut counts = vec![0; bins + 2];
+        let mut distances = Vec::new();
+        while let Some(batch) = batches.next_batch() {
+            for &i in &rows[batch] {
+                let zero = T::Accumulator::ZERO;
+                let (d, _) = nearest_neighbour(&ar1, &ar2, metric, i, inner_rows, zero)
+                    .expect("no bound to break on");
+                let d = metric
+                    .distance(d)
+                    .to_f64()
+                    .expect("a floating point distance");
+                counts[histogram.position(d)] += 1;
+                distances.push((i, d));
+            }
+        }
+        (counts, distances)
+    };
+    let searched = if workers <= 1 {
+        // single thread/serial approach
+        vec![search(0)]
+    } else {
+        executor.run_workers(workers, search)?
+    };
+    let mut counts = vec![0; bins + 2];
+    let mut distances = Vec::with_capacity(rows.len());
+    for (worker_counts, worker_distances) in searched {
+        for (count, worker_count) in counts.iter_mut().zip(worker_counts) {
+            *count += worker_count;
+        }
+        distances.extend(worker_distances);
+    }
+
+    // summed in the order of the rows, for reproducible sums
+    distances.sort_unstable_by_key(|&(i, _)| i);
+    let mut distances: Vec<_> = distances.into_iter().map(|(_, d)| d).collect();
+    let n = distances.len() as f64;
+    let mean = distances.iter().sum::<f64>() / n;
+    let rms = (distances.iter().map(|d| d * d).sum::<f64>() / n).sqrt();
+    distances.sort_unstable_by(f64::total_cmp);
+    let median = distances[nearest_rank(0.5, distances.len())? - 1];
+    Ok(DistanceDistribution {
+        edges: histogram.edges.clone().into(),
+        counts: counts[1..=bins].to_vec().into(),
+        below: counts[0],
+        above: counts[bins + 1],
+        mean,
+        rms,
+        median,
+        max: distances[distances.len() - 1],
+        quantiles: ranks.iter().map(|&k| distances[k - 1]).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NonFinitePolicy, Shuffle};
+    use ndarray::prelude::*;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(23);
+        let a1 = Array2::from_shape_fn((250, 3), |_| rng.gen_range(-1.0..1.0));
+        let a2 = Array2::from_shape_fn((60, 3), |_| rng.gen_range(-1.0..1.0));
+        let distances: Vec<f64> = a1
+            .rows()
+            .into_iter()
+            .map(|x| {
+                a2.rows()
+                    .into_iter()
+                    .map(|y| f64::sqrt((&x - &y).mapv(|c| c * c).sum()))
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .collect();
+        let mut sorted = distances.clone();
+        sorted.sort_unstable_by(f64::total_cmp);
+        let edges = [0.1, 0.2, 0.4, 0.8];
+        let count = |lo: f64, hi: f64| distances.iter().filter(|&&d| lo <= d && d < hi).count();
+        let histogram = Histogram::new(edges).quantiles([0.0, 0.95, 1.0]);
+        let serial = HausdorffOptions::new().workers(1);
+        let expected =
+            try_directed_distance_distribution_with(&a1, &a2, &histogram, &serial).unwrap();
+        assert_eq!(expected.edges, arr1(&edges));
+        assert_eq!(
+            expected.counts,
+            arr1(&[count(0.1, 0.2), count(0.2, 0.4), count(0.4, 0.8)])
+        );
+        assert_eq!(expected.below, count(0.0, 0.1));
+        assert_eq!(expected.above, count(0.8, f64::INFINITY));
+        assert_eq!(expected.cumulative_counts()[[2]], 250 - expected.above);
+        let mean = distances.iter().sum::<f64>() / 250.0;
+        assert!((expected.mean - mean).abs() < 1e-12);
+        let rms = (distances.iter().map(|d| d * d).sum::<f64>() / 250.0).sqrt();
+        assert!((expected.rms - rms).abs() < 1e-12);
+        assert_eq!(expected.median, sorted[124]);
+        assert_eq!(expected.max, sorted[249]);
+        assert_eq!(
+            expected.quantiles,
+            arr1(&[sorted[0], sorted[237], sorted[249]])
+        );
+        // bit for bit the same, however the work is divided
+        for workers in [0, 2, 5] {
+            for shuffle in [Shuffle::SciPy(5), Shuffle::Disabled] {
+                let options = serial.clone().workers(workers).shuffle(shuffle);
+                let actual =
+                    try_directed_distance_distribution_with(&a1, &a2, &histogram, &options);
+                assert_eq!(actual.as_ref(), Ok(&expected));
+            }
+        }
+    }
+
+    #[test]
+    fn bin_edges() {
+        let histogram = Histogram::uniform(1.0, 3.0, 2);
+        assert_eq!(histogram.edges, [1.0, 2.0, 3.0]);
+        let positions = [0.5, 1.0, 1.5, 2.0, 3.0, 3.5].map(|d| histogram.position(d));
+        assert_eq!(positions, [0, 1, 1, 2, 2, 3]);
+        assert!(std::panic::catch_unwind(|| Histogram::new([1.0])).is_err());
+        assert!(std::panic::catch_unwind(|| Histogram::new([1.0, 1.0])).is_err());
+        assert!(std::panic::catch_unwind(|| Histogram::new([0.0, f64::NAN])).is_err());
+        assert!(std::panic::catch_unwind(|| Histogram::uniform(0.0, 1.0, 0)).is_err());
+    }
+
+    #[test]
+    fn numpy_histogram() {
+        // the examples of the numpy.histogram documentation,
+        // as distances from the origin along a line:
+        // np.histogram([1, 2, 1], bins=[0, 1, 2, 3]) counts [0, 2, 1]
+        // np.histogram([[1, 2, 1], [1, 0, 1]], bins=[0, 1, 2, 3]) counts [1, 4, 1]
+        let origin = arr2(&[[0.0]]);
+        let histogram = Histogram::new([0.0, 1.0, 2.0, 3.0]);
+        let options = HausdorffOptions::new();
+        let counts = |values: &[f64]| {
+            let points = Array2::from_shape_vec((values.len(), 1), values.to_vec()).unwrap();
+            try_directed_distance_distribution_with(&points, &origin, &histogram, &options).unwrap()
+        };
+        assert_eq!(counts(&[1.0, 2.0, 1.0]).counts, arr1(&[0, 2, 1]));
+        assert_eq!(
+            counts(&[1.0, 2.0, 1.0, 1.0, 0.0, 1.0]).counts,
+            arr1(&[1, 4, 1])
+        );
+        // numpy's last bin includes its upper edge, and numpy
+        // ignores the values outside the range, which we count apart
+        let distribution = counts(&[3.0, 3.5, 1.0]);
+        assert_eq!(distribution.counts, arr1(&[0, 1, 1]));
+        assert_eq!((distribution.below, distribution.above), (0, 1));
+    }
+
+    #[test]
+    fn non_finite_policies() {
+        let a1 = arr2(&[[0.0, 0.0], [f64::NAN, 1.0], [3.0, 4.0]]);
+        let a2 = arr2(&[[0.0, 0.0]]);
+        let histogram = Histogram::new([0.0, 10.0]).quantiles([0.5, 1.5]);
+        let options = HausdorffOptions::new().non_finite_policy(NonFinitePolicy::Skip);
+        let distribution = |options| {
+            let histogram = histogram.clone().quantiles([1.0]);
+            try_directed_distance_distribution_with(&a1, &a2, &histogram, options)
+        };
+        let skipped = distribution(&options).unwrap();
+        assert_eq!(skipped.counts, arr1(&[2]));
+        assert_eq!((skipped.mean, skipped.median, skipped.max), (2.5, 0.0, 5.0));
+        let actual = try_directed_distance_distribution_with(&a1, &a2, &histogram, &options);
+        let invalid = "the quantile 1.5 is not between 0 and 1".to_string();
+        assert_eq!(actual, Err(HausdorffError::InvalidRank(invalid)));
+        let propagate = options
+            .clone()
+            .non_finite_policy(NonFinitePolicy::Propagate);
+        let propagated = distribution(&propagate).unwrap();
+        assert_eq!(propagated.counts, arr1(&[0]));
+        assert!(propagated.mean.is_nan() && propagated.max.is_nan());
+        assert!(propagated.quantiles[[0]].is_nan());
+    }
+}
@@ -0,0 +1,119 @@
+//! Checks of the input arrays and the non-finite policy.
+
+use crate::coordinate::Coordinate;
+use crate::error::HausdorffError;
+use crate::metric::Metric;
+use crate::options::NonFinitePolicy;
+use crate::result::HausdorffResult;
+use ndarray::ArrayView2;
+
+/// Check that both arrays hold a non-empty set
+/// of points of the same dimension.
+pub(crate) fn check_shapes<T>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+) -> Result<(), HausdorffError> {
+    if ar1.ncols() != ar2.ncols() {
+        return Err(HausdorffError::DimensionMismatch {
+            ar1_columns: ar1.ncols(),
+            ar2_columns: ar2.ncols(),
+        });
+    }
+    if ar1.nrows() == 0 {
+        return Err(HausdorffError::EmptyInput { argument: "ar1" });
+    }
+    if ar2.nrows() == 0 {
+        return Err(HausdorffError::EmptyInput { argument: "ar2" });
+    }
+    Ok(())
+}
+
+/// Apply the non-finite `policy`, removing rows from the orderings
+/// `rows1` and `rows2` of `ar1` and `ar2` if they are to be skipped.
+/// Returns the NaN result to report if non-finite values propagate.
+pub(crate) fn apply_non_finite_policy<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+    policy: NonFinitePolicy,
+    rows1: &mut Vec<usize>,
+    rows2: &mut Vec<usize>,
+) -> Result<Option<HausdorffResult<T>>, HausdorffError> {
+    match policy {
+        NonFinitePolicy::Reject => {
+            reject_non_finite("ar1", ar1)?;
+            reject_non_finite("ar2", ar2)?;
+        }
+        NonFinitePolicy::Skip => {
+            retain_finite_rows("ar1", ar1, rows1)?;
+            retain_finite_rows("ar2", ar2, rows2)?;
+        }
+        NonFinitePolicy::Propagate => return Ok(propagate_non_finite(ar1, ar2, metric)),
+    }
+    Ok(None)
+}
+
+/// The position and value of the first non-finite coordinate of `ar`.
+pub(crate) fn first_non_finite<T: Coordinate>(ar: &ArrayView2<T>) -> Option<((usize, usize), T)> {
+    ar.indexed_iter()
+        .find(|(_, x)| !x.is_finite())
+        .map(|(index, x)| (index, *x))
+}
+
+fn reject_non_finite<T: Coordinate>(
+    argument: &'static str,
+    ar: &ArrayView2<T>,
+) -> Result<(), HausdorffError> {
+    match first_non_finite(ar) {
+        Some(((row, column), _)) => Err(HausdorffError::NonFinite {
+            argument,
+            row,
+            column,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Keep only the `rows` of `ar` whose coordinates are all finite.
+pub(crate) fn retain_finite_rows<T: Coordinate>(
+    argument: &'static str,
+    ar: &ArrayView2<T>,
+    rows: &mut Vec<usize>,
+) -> Result<(), HausdorffError> {
+    rows.retain(|&i| ar.row(i).iter().all(|x| x.is_finite()));
+    if rows.is_empty() {
+        return Err(HausdorffError::EmptyInput { argument });
+    }
+    Ok(())
+}
+
+/// A NaN result pointing at the first non-finite row, if any.
+pub(crate) fn propagate_non_finite<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+) -> Option<HausdorffResult<T>> {
+    // the squared difference of a non-finite
+    // coordinate with itself is always NaN
+    if let Some(((row, _), x)) = first_non_finite(ar1) {
+        return Some(HausdorffResult::new(
+            ar1,
+            ar2,
+            metric,
+            x.squared_difference(x),
+            row,
+            0,
+        ));
+    }
+    if let Some(((row, _), x)) = first_non_finite(ar2) {
+        return Some(HausdorffResult::new(
+            ar1,
+            ar2,
+            metric,
+            x.squared_difference(x),
+            0,
+            row,
+        ));
+    }
+    None
+}
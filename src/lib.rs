@@ -5,20 +5,89 @@
 //! of the two arrays of points is quite large, since this allows
 //! the calculation to be effectively distributed over several
 //! threads.
+//!
+//! Points may be stored with any [`Coordinate`] type, which
+//! includes `f32` and `f64` (and half precision `half::f16` with
+//! the `half` feature enabled), as well as integer types such as
+//! voxel indices, for which squared distances are exact.
+//! Distances are Euclidean by default, or measured by any other
+//! [`Metric`], such as [`Manhattan`] or [`Chebyshev`].
+//!
+//! The work is distributed over scoped standard library threads by
+//! default, or over a rayon thread pool with the `rayon` feature
+//! enabled (see [`Backend`]).
+
+mod average;
+mod chamfer;
+mod coordinate;
+mod cutoff;
+mod decision;
+mod early_break;
+mod engine;
+mod error;
+#[cfg(test)]
+mod fixtures;
+mod histogram;
+mod input;
+mod metric;
+mod nearest;
+mod options;
+mod parallel;
+mod partial;
+mod result;
+mod shuffle;
+mod symmetric;
 
-use ndarray::Array2;
-use rand::{seq::SliceRandom, SeedableRng};
-use rand_chacha::ChaChaRng;
-use std::sync::mpsc;
+pub use average::{try_average_hausdorff_with, try_directed_average_hausdorff_with, Averaging};
+pub use chamfer::{try_chamfer_distance_with, try_directed_chamfer_distance_with, Chamfer};
+pub use coordinate::{Accumulator, Coordinate, Distance};
+pub use cutoff::{try_directed_hausdorff_bounded_with, try_hausdorff_bounded_with};
+pub use decision::{try_directed_hausdorff_within_with, try_hausdorff_within_with};
+use early_break::{
+    directed_hausdorff_batches, directed_hausdorff_core, merge, SharedBound, Witness,
+};
+pub use engine::HausdorffEngine;
+pub use error::HausdorffError;
+pub use histogram::{try_directed_distance_distribution_with, Histogram};
+use input::{apply_non_finite_policy, check_shapes};
+pub use metric::{
+    AngleUnit, Chebyshev, Euclidean, Haversine, Mahalanobis, Manhattan, Metric, Minkowski,
+    PeriodicBox, Spacing, WeightedEuclidean,
+};
+use ndarray::{Array2, ArrayView2, AsArray, Ix2};
+pub use nearest::{try_nearest_neighbours_beyond_with, try_nearest_neighbours_with};
+pub use options::{AutoWorkers, HausdorffOptions, NonFinitePolicy, Workers};
+pub use parallel::Backend;
+use parallel::{estimated_work, Batches, Executor};
+pub use partial::{
+    try_directed_partial_hausdorff_with, try_farthest_points_with, Partial, PartialStrategy,
+};
+pub use result::{
+    Bounded, Decision, Direction, DistanceDistribution, HausdorffResult, Outliers,
+    SymmetricHausdorffResult,
+};
+use shuffle::RowOrders;
+pub use shuffle::Shuffle;
 use std::sync::Arc;
-use std::thread;
+pub use symmetric::{hausdorff, try_hausdorff, try_hausdorff_with};
 
 /// Calculate the directed Hausdorff distance between two arrays.
 ///
-/// The current API accepts only atomically reference counted
-/// arrays to simplify the initial design around providing
-/// convenient parallel performance. The number of threads
-/// may be specified with the `workers` argument.
+/// This entry point accepts atomically reference counted
+/// arrays, which was the original design around providing
+/// convenient parallel performance. Callers holding borrowed
+/// data (slices, column subsets, views into larger buffers)
+/// should prefer [`directed_hausdorff_view`], which avoids
+/// the copy into an owned array. The number of threads
+/// may be specified with the `workers` argument, with `0`
+/// using the available parallelism of the machine.
+///
+/// For compatibility, the result is returned as a
+/// `(distance, ar1_index, ar2_index)` tuple, where `ar1_index`
+/// is the row of `ar1` farthest from `ar2` and `ar2_index` is
+/// the row of its nearest neighbour in `ar2`. The view based
+/// entry point returns a [`HausdorffResult`] instead, which also
+/// carries the reduced distance and the witness coordinates.
 ///
 /// The implementation is a parallel intrepretation of the
 /// "early break" algorithm described in the following publication:
@@ -41,9 +110,10 @@ use std::thread;
 /// # use ndarray::prelude::*;
 /// let a1 = arr2(&[[1., 2., 3.], [4., 5., 6.]]);
 /// let a2 = arr2(&[[1., 2., 3.], [4., 5., 6.]]);
-/// // we will ignore the indices in the result tuple for now
-/// let dist = directed_hausdorff(Arc::new(a1), Arc::new(a2), 1).0;
+/// let (dist, ar1_index, ar2_index) = directed_hausdorff(Arc::new(a1), Arc::new(a2), 1);
 /// assert_eq!(dist, 0.0);
+/// // each point is its own nearest neighbour
+/// assert_eq!(ar1_index, ar2_index);
 /// ```
 ///
 /// ```
@@ -60,124 +130,255 @@ use std::thread;
 /// let dist_2 = directed_hausdorff(a2.clone(), a1.clone(), 2).0;
 /// assert_eq!(dist_1, 2.23606797749979);
 /// assert_eq!(dist_2, 3.0);
-/// // the general Hausdorff distance is the max of the two
+/// // the general Hausdorff distance is the max of the two,
+/// // which `hausdorff()` calculates in a single pass
 /// let general_dist = dist_1.max(dist_2);
 /// assert_eq!(general_dist, 3.0);
+/// assert_eq!(hausdorff(a1.view(), a2.view(), 2).distance, general_dist);
 /// ```
 ///
-
+/// # Panics
+///
+/// Panics under the same conditions as [`directed_hausdorff_view`].
 pub fn directed_hausdorff(
     ar1: Arc<Array2<f64>>,
     ar2: Arc<Array2<f64>>,
     workers: usize,
 ) -> (f64, usize, usize) {
-    if workers <= 1 {
-        // single thread/serial approach
-        directed_hausdorff_core(&ar1, &ar2, 0, ar1.nrows())
-    } else {
-        let chunk_size;
-        if ar1.nrows() % workers == 0 {
-            chunk_size = ar1.nrows() / workers;
-        } else {
-            chunk_size = match workers >= ar1.nrows() {
-                // if there are equivalent or more workers than rows
-                // give each worker a row until you run
-                // out of rows
-                true => 1,
-                // NOTE: there is probably a more efficient
-                // way to distribute work in this case
-                false => (ar1.nrows() as f64 / workers as f64).ceil() as usize,
-            };
-        }
-        let rx = {
-            let (tx, rx) = mpsc::channel();
-            let mut start = 0;
-            let mut stop = chunk_size;
-            for _ in 0..workers {
-                let sub_tx = tx.clone();
-                let arr1 = ar1.clone();
-                let arr2 = ar2.clone();
-                thread::spawn(move || {
-                    let thread_result = directed_hausdorff_core(&arr1, &arr2, start, stop);
-                    sub_tx.send(thread_result).unwrap();
-                });
-                start += chunk_size;
-                stop += chunk_size;
-                if stop > ar1.nrows() {
-                    stop = ar1.nrows();
-                }
-            }
-            rx
-        };
-        let mut results = vec![];
-        for thread_val in rx.iter() {
-            results.push(thread_val);
-        }
-        // reverse sort the vector of hausdorff tuples
-        // and return the largest (true Hausdorff)
-        // distance data from the threads
-        results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
-        results[0]
-    }
+    directed_hausdorff_view(ar1.view(), ar2.view(), workers).into_tuple()
 }
 
-fn directed_hausdorff_core(
-    ar1: &Array2<f64>,
-    ar2: &Array2<f64>,
-    start_row_index: usize,
-    end_row_index: usize,
-) -> (f64, usize, usize) {
-    let mut cmax = 0.0;
-    let mut d = 0.0;
-    let num_dims = ar1.shape()[1];
-    let mut i_store = 0;
-    let mut j_store = 0;
-    let mut i_ret = 0;
-    let mut j_ret = 0;
-
-    // algorithm is known to experience more
-    // early breaks if data is not "structured"
-    // so shuffle it as a general rule
-    let seed = [0; 32];
-    let mut rng = ChaChaRng::from_seed(seed);
-    let mut rng2 = ChaChaRng::from_seed(seed);
-    let mut iter_elements: Vec<usize> = (start_row_index..end_row_index).collect();
-    let mut inner_iter_elements: Vec<usize> = (0..ar2.nrows()).collect();
-    iter_elements.shuffle(&mut rng);
-    inner_iter_elements.shuffle(&mut rng2);
-
-    for i in iter_elements {
-        let mut cmin = f64::INFINITY;
-        for j in &inner_iter_elements {
-            d = 0.0;
-            for dim in 0..num_dims {
-                // square of distance -- avoid sqrt
-                // until very end for performance
-                d += (ar1[[i, dim]] - ar2[[*j, dim]]).powi(2);
-            }
-            if d < cmax {
-                break;
-            }
-            if d < cmin {
-                cmin = d;
-                i_store = i;
-                j_store = *j;
-            }
-        }
-        // Note: The reference paper by A. A. Taha and A. Hanbury has this line
-        // (Algorithm 2, line 16) as:
-        //
-        // if cmin > cmax:
-        //
-        // That logic is incorrect, as cmin could still be np.inf if breaking early.
-        // The logic here accounts for that case.
-        if cmin >= cmax && d >= cmax {
-            cmax = cmin;
-            i_ret = i_store;
-            j_ret = j_store;
-        }
+/// Calculate the directed Hausdorff distance between two borrowed arrays.
+///
+/// Accepts anything implementing [`AsArray`], such as `&Array2<f64>`
+/// or an [`ArrayView2`] produced by slicing a larger array, so
+/// no copy into an owned, reference counted array is required.
+/// The parallel path uses scoped threads that borrow the input
+/// data directly for the duration of the call.
+///
+/// The coordinates may be of any [`Coordinate`] type, so i.e.,
+/// single precision point clouds do not need to be widened to
+/// `f64` first. Squared distances are accumulated in the
+/// coordinate type's [`Accumulator`], and the returned distance
+/// is of type [`Distance<T>`].
+///
+/// The algorithm and the meaning of `workers` are the same as
+/// for [`directed_hausdorff`].
+///
+/// # Panics
+///
+/// Panics if the inputs are invalid, i.e., if either array is
+/// empty, the arrays have different numbers of columns or a
+/// coordinate is not finite. Use [`try_directed_hausdorff`] to
+/// handle these cases as a [`HausdorffError`] instead.
+///
+/// # Examples
+///
+/// ```
+/// // compare only the first two columns of a wider array,
+/// // without copying the column subset
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[1.0, 0.0, 9.0], [0.0, 3.0, 9.0], [-1.0, 0.0, 9.0]]);
+/// let a2 = arr2(&[[2.0, 0.0], [0.0, 0.0], [-2.0, 0.0]]);
+/// let result = directed_hausdorff_view(a1.slice(s![.., ..2]), &a2, 2);
+/// assert_eq!(result.distance, 3.0);
+/// assert_eq!(result.reduced_distance, 9.0);
+/// // the witness pair realising the distance
+/// assert_eq!((result.ar1_index, result.ar2_index), (1, 1));
+/// assert_eq!(result.ar1_point, arr1(&[0.0, 3.0]));
+/// assert_eq!(result.ar2_point, arr1(&[0.0, 0.0]));
+/// ```
+///
+/// ```
+/// // single precision input produces a single precision distance
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[1.0_f32, 0.0], [0.0, 1.0], [-1.0, 0.0], [0.0, -1.0]]);
+/// let a2 = arr2(&[[2.0_f32, 0.0], [0.0, 2.0], [-2.0, 0.0], [0.0, -4.0]]);
+/// let dist: f32 = directed_hausdorff_view(&a1, &a2, 2).distance;
+/// assert_eq!(dist, 5.0_f32.sqrt());
+/// ```
+pub fn directed_hausdorff_view<'a, 'b, T, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    workers: usize,
+) -> HausdorffResult<T>
+where
+    T: Coordinate + 'a + 'b,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    try_directed_hausdorff(ar1, ar2, workers).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Calculate the directed Hausdorff distance between two borrowed
+/// arrays, reporting invalid inputs as a [`HausdorffError`].
+///
+/// This is the fallible counterpart of [`directed_hausdorff_view`],
+/// for i.e., services handling untrusted uploads, where a bad input
+/// should not crash the process. Both arrays must be non-empty,
+/// have the same number of columns and contain only finite
+/// coordinates. A panic in a worker thread is reported as
+/// [`HausdorffError::WorkerFailed`].
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[1.0, 0.0], [0.0, f64::NAN]]);
+/// let a2 = arr2(&[[2.0, 0.0], [0.0, 2.0]]);
+/// let err = try_directed_hausdorff(&a1, &a2, 2).unwrap_err();
+/// assert_eq!(
+///     err,
+///     HausdorffError::NonFinite {
+///         argument: "ar1",
+///         row: 1,
+///         column: 1
+///     }
+/// );
+///
+/// let a3 = arr2(&[[2.0, 0.0, 1.0]]);
+/// assert!(try_directed_hausdorff(&a2, &a3, 2).is_err());
+/// ```
+pub fn try_directed_hausdorff<'a, 'b, T, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    workers: usize,
+) -> Result<HausdorffResult<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    try_directed_hausdorff_with(ar1, ar2, &HausdorffOptions::new().workers(workers))
+}
+
+/// Calculate the directed Hausdorff distance between two borrowed
+/// arrays, as configured by [`HausdorffOptions`].
+///
+/// The [`NonFinitePolicy`] is applied before the work is
+/// distributed, so the serial path and every worker see the
+/// same set of points.
+///
+/// The rows of `ar1` are visited in the order given by the
+/// [`Shuffle`], and handed out to the workers in small batches of
+/// consecutive rows of that order, so that no worker sits idle while
+/// another is still searching rows that do not break early. In case
+/// of ties, the reported witness pair is the one the serial
+/// calculation would find, so the result does not depend on the
+/// number of workers or on how the batches were divided.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// // a missing sensor return in the first array
+/// let a1 = arr2(&[[1.0, 0.0], [f64::NAN, f64::NAN], [0.0, 1.0]]);
+/// let a2 = arr2(&[[2.0, 0.0], [0.0, 2.0]]);
+/// let options = HausdorffOptions::new()
+///     .workers(2)
+///     .non_finite_policy(NonFinitePolicy::Skip);
+/// let result = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+/// assert_eq!(result.distance, 1.0);
+///
+/// let options = options.non_finite_policy(NonFinitePolicy::Propagate);
+/// let result = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+/// assert!(result.distance.is_nan());
+/// assert_eq!(result.ar1_index, 1);
+/// ```
+///
+/// ```
+/// // reproduce scipy.spatial.distance.directed_hausdorff(a1, a2, seed=1)
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[1., 2., 3.], [4., 5., 6.]]);
+/// let options = HausdorffOptions::new().shuffle(Shuffle::SciPy(1));
+/// let result = try_directed_hausdorff_with(&a1, &a1, &options).unwrap();
+/// assert_eq!(result.into_tuple(), (0.0, 1, 1));
+/// ```
+pub fn try_directed_hausdorff_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    options: &HausdorffOptions<M>,
+) -> Result<HausdorffResult<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    directed_with(
+        ar1.into(),
+        ar2.into(),
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// The directed calculation behind both [`try_directed_hausdorff_with`]
+/// and [`HausdorffEngine::directed`].
+fn directed_with<T: Coordinate, M: Metric<T>>(
+    ar1: ArrayView2<T>,
+    ar2: ArrayView2<T>,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+    orders: &mut RowOrders,
+) -> Result<HausdorffResult<T>, HausdorffError> {
+    check_shapes(&ar1, &ar2)?;
+    let metric = &options.metric;
+    metric.check_columns(ar1.ncols())?;
+    orders.prepare_forward(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    let RowOrders {
+        rows1, inner_rows2, ..
+    } = orders;
+    let policy = options.non_finite_policy;
+    if let Some(result) = apply_non_finite_policy(&ar1, &ar2, metric, policy, rows1, inner_rows2)? {
+        return Ok(result);
     }
-    (cmax.sqrt(), i_ret, j_ret)
+    let (reduced_distance, i, j) =
+        directed_hausdorff_rows(&ar1, &ar2, rows1, inner_rows2, options, executor)?;
+    Ok(HausdorffResult::new(
+        &ar1,
+        &ar2,
+        metric,
+        reduced_distance,
+        i,
+        j,
+    ))
+}
+
+/// Distribute the rows of `ar1` listed in `rows` over the worker
+/// threads, each searching the rows of `ar2` listed in `inner_rows`.
+fn directed_hausdorff_rows<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    rows: &[usize],
+    inner_rows: &[usize],
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+) -> Result<Witness<T::Accumulator>, HausdorffError> {
+    let metric = &options.metric;
+    let zero = T::Accumulator::ZERO;
+    let work = estimated_work(rows.len(), inner_rows.len(), ar1.ncols());
+    let available = executor.available_workers();
+    let workers = options.workers.resolve(rows.len(), work, available);
+    let result = if workers <= 1 {
+        // single thread/serial approach
+        directed_hausdorff_core(ar1, ar2, metric, rows, inner_rows, zero, None)
+    } else {
+        let batches = Batches::new(rows.len(), workers);
+        let shared = SharedBound::new(zero);
+        let shared = options.shared_bound.then_some(&shared);
+        merge(executor.run_workers(workers, |_| {
+            directed_hausdorff_batches(ar1, ar2, metric, rows, inner_rows, &batches, zero, shared)
+        })?)
+    };
+    Ok(result.expect("non-empty rows"))
 }
 
 #[cfg(test)]
@@ -199,6 +400,424 @@ mod tests {
         );
     }
 
+    #[test]
+    fn identical_arrays_scipy_seeds() {
+        // with several equally distant witnesses, the
+        // reported pair depends on the shuffle; these
+        // match SciPy directed_hausdorff(arr, arr, seed=...)
+        let a1 = arr2(&[[1., 2., 3.], [4., 5., 6.]]);
+        for (seed, expected) in [(0, (0.0, 0, 0)), (1, (0.0, 1, 1))] {
+            for workers in 0..9 {
+                let options = HausdorffOptions::new()
+                    .workers(workers)
+                    .shuffle(Shuffle::SciPy(seed));
+                let actual = try_directed_hausdorff_with(&a1, &a1, &options).unwrap();
+                assert_eq!(actual.into_tuple(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn shuffle_independent_of_workers() {
+        // the reported witness is the one found by the
+        // serial calculation, whatever the shuffle
+        let a1 = arr2(&[
+            [0., 0.],
+            [1., 0.],
+            [0., 1.],
+            [1., 1.],
+            [2., 0.],
+            [0., 2.],
+            [2., 2.],
+        ]);
+        let a2 = arr2(&[[0.5, 0.5], [1.5, 1.5], [0.5, 1.5], [1.5, 0.5]]);
+        let shuffles = [
+            Shuffle::default(),
+            Shuffle::ChaCha([7; 32]),
+            Shuffle::SciPy(12345),
+            Shuffle::Disabled,
+            Shuffle::Permutation {
+                ar1: vec![6, 5, 4, 3, 2, 1, 0],
+                ar2: vec![3, 1, 2, 0],
+            },
+        ];
+        for shuffle in shuffles {
+            let options = HausdorffOptions::new().shuffle(shuffle);
+            let expected = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+            assert_eq!(expected.reduced_distance, 0.5);
+            for workers in 2..9 {
+                let options = options.clone().workers(workers);
+                let actual = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+                assert_eq!(actual, expected);
+                let options = options.shared_bound(true);
+                let actual = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+                assert_eq!(actual, expected);
+            }
+        }
+        // without shuffling, the last of the equally
+        // distant rows and its first nearest neighbour
+        let options = HausdorffOptions::new().shuffle(Shuffle::Disabled);
+        let actual = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+        assert_eq!((actual.ar1_index, actual.ar2_index), (6, 1));
+        let options = HausdorffOptions::new().shuffle(Shuffle::Permutation {
+            ar1: vec![0, 1, 2],
+            ar2: vec![0, 1, 2, 3],
+        });
+        assert_eq!(
+            try_directed_hausdorff_with(&a1, &a2, &options),
+            Err(HausdorffError::InvalidPermutation {
+                argument: "ar1",
+                rows: 7
+            })
+        );
+    }
+
+    #[test]
+    fn shared_bound_matches_local_bounds() {
+        // small integer coordinates produce many equally distant
+        // rows, so the witness depends on the tie-breaking, which
+        // must not depend on how the batches were divided either
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(9);
+        for _ in 0..20 {
+            let a1 = Array2::from_shape_fn((200, 2), |_| rng.gen_range(0_i16..12));
+            let a2 = Array2::from_shape_fn((50, 2), |_| rng.gen_range(0_i16..12));
+            let serial = HausdorffOptions::new().workers(1);
+            let expected = try_directed_hausdorff_with(&a1, &a2, &serial);
+            for workers in 2..9 {
+                let options = HausdorffOptions::new().workers(workers);
+                let shared = options.clone().shared_bound(true);
+                assert_eq!(try_directed_hausdorff_with(&a1, &a2, &options), expected);
+                assert_eq!(try_directed_hausdorff_with(&a1, &a2, &shared), expected);
+                for prune in [false, true] {
+                    let options = options.clone().symmetric_pruning(prune);
+                    let shared = shared.clone().symmetric_pruning(prune);
+                    let expected = try_hausdorff_with(&a1, &a2, &options).unwrap();
+                    let actual = try_hausdorff_with(&a1, &a2, &shared).unwrap();
+                    assert_eq!(actual.distance, expected.distance);
+                    assert_eq!(actual.witness(), expected.witness());
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn rayon_backend_matches_threads() {
+        let a1 = arr2(&[
+            [0., 0.],
+            [1., 0.],
+            [0., 1.],
+            [1., 1.],
+            [2., 0.],
+            [0., 2.],
+            [2., 2.],
+        ]);
+        let a2 = arr2(&[[0.5, 0.5], [1.5, 1.5], [0.5, 1.5], [1.5, 0.5]]);
+        let pool = std::sync::Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(3)
+                .build()
+                .unwrap(),
+        );
+        for workers in 0..9 {
+            let options = HausdorffOptions::new().workers(workers);
+            let expected = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+            let symmetric = try_hausdorff_with(&a1, &a2, &options).unwrap();
+            for backend in [Backend::Rayon, Backend::RayonPool(pool.clone())] {
+                let options = options.clone().backend(backend);
+                assert_eq!(
+                    try_directed_hausdorff_with(&a1, &a2, &options),
+                    Ok(expected.clone())
+                );
+                assert_eq!(
+                    try_hausdorff_with(&a1, &a2, &options),
+                    Ok(symmetric.clone())
+                );
+                // the calculation may itself run on the pool
+                let nested = pool.install(|| try_directed_hausdorff_with(&a1, &a2, &options));
+                assert_eq!(nested, Ok(expected.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn auto_workers_match_serial() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(13);
+        let serial = HausdorffOptions::new().workers(1);
+        for rows in [1, 10, 100, 1000] {
+            let a1 = Array2::from_shape_fn((rows, 3), |_| rng.gen_range(0_u8..20));
+            let a2 = Array2::from_shape_fn((50, 3), |_| rng.gen_range(0_u8..20));
+            let expected = try_directed_hausdorff_with(&a1, &a2, &serial);
+            let symmetric = try_hausdorff_with(&a1, &a2, &serial);
+            // from serial for 1 row up to 8 workers for 1000 rows
+            let auto = AutoWorkers::new()
+                .serial_below(500)
+                .work_per_worker(15_000)
+                .max_workers(8);
+            for workers in [Workers::auto(), Workers::Auto(auto)] {
+                let options = HausdorffOptions::new().workers(workers);
+                assert_eq!(try_directed_hausdorff_with(&a1, &a2, &options), expected);
+                assert_eq!(try_hausdorff_with(&a1, &a2, &options), symmetric);
+                let mut engine = HausdorffEngine::new(options);
+                assert_eq!(engine.directed(&a1, &a2), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn metrics_match_brute_force() {
+        use rand::{Rng, SeedableRng};
+        fn check<M: Metric<f64> + Clone>(metric: M, a1: &Array2<f64>, a2: &Array2<f64>) {
+            let nearest = |a: &Array2<f64>, b: &Array2<f64>| {
+                a.rows()
+                    .into_iter()
+                    .map(|x| {
+                        b.rows()
+                            .into_iter()
+                            .map(|y| metric.distance(metric.reduced_distance(x, y)))
+                            .fold(f64::INFINITY, f64::min)
+                    })
+                    .fold(0.0, f64::max)
+            };
+            let (forward, reverse) = (nearest(a1, a2), nearest(a2, a1));
+            let serial = HausdorffOptions::new().workers(1).metric(metric);
+            let expected = try_directed_hausdorff_with(a1, a2, &serial).unwrap();
+            assert_eq!(expected.distance, forward);
+            let symmetric = try_hausdorff_with(a1, a2, &serial).unwrap();
+            assert_eq!(symmetric.distance, forward.max(reverse));
+            for workers in [0, 2, 5] {
+                let options = serial.clone().workers(workers).shared_bound(true);
+                assert_eq!(
+                    try_directed_hausdorff_with(a1, a2, &options),
+                    Ok(expected.clone())
+                );
+                assert_eq!(try_hausdorff_with(a1, a2, &options), Ok(symmetric.clone()));
+                let mut engine = HausdorffEngine::new(options);
+                assert_eq!(engine.directed(a1, a2), Ok(expected.clone()));
+            }
+        }
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(14);
+        let a1 = Array2::from_shape_fn((200, 3), |_| rng.gen_range(-1.0..1.0));
+        let a2 = Array2::from_shape_fn((50, 3), |_| rng.gen_range(-1.0..1.0));
+        check(Euclidean, &a1, &a2);
+        check(Manhattan, &a1, &a2);
+        check(Chebyshev, &a1, &a2);
+        check(Minkowski::new(3.0), &a1, &a2);
+        check(WeightedEuclidean::new([1.0, 0.5, 4.0]), &a1, &a2);
+        // the metric must fit the points
+        let options = HausdorffOptions::new().metric(WeightedEuclidean::new([1.0, 1.0]));
+        let err = HausdorffError::InvalidMetric("2 weights for points with 3 columns".into());
+        assert_eq!(
+            try_directed_hausdorff_with(&a1, &a2, &options),
+            Err(err.clone())
+        );
+        assert_eq!(try_hausdorff_with(&a1, &a2, &options), Err(err));
+    }
+
+    #[test]
+    fn view_matches_arc() {
+        // borrowed inputs, including non-contiguous
+        // views into a larger buffer, should produce the
+        // same result as the reference counted entry point
+        let a1 = arr2(&[[0., 0.7], [1., 6.5], [0., 17.], [-1., 9.]]);
+        let a2 = arr2(&[[77., 7.2], [15., 5.5], [-9., 16.]]);
+        let mut buffer = Array2::<f64>::zeros((8, 5));
+        buffer.slice_mut(s![..;2, 1..3]).assign(&a1);
+        let strided = buffer.slice(s![..;2, 1..3]);
+        let expected = (15.749285698088025, 0, 1);
+        for workers in 0..9 {
+            assert_eq!(
+                directed_hausdorff_view(&a1, &a2, workers).into_tuple(),
+                expected
+            );
+            assert_eq!(
+                directed_hausdorff_view(strided, a2.view(), workers),
+                directed_hausdorff_view(&a1, &a2, workers)
+            );
+            assert_eq!(
+                directed_hausdorff_view(&a1, &a2, workers).into_tuple(),
+                directed_hausdorff(Arc::new(a1.clone()), Arc::new(a2.clone()), workers)
+            );
+        }
+    }
+
+    #[test]
+    fn single_precision_matches_double() {
+        let a1: Array2<f64> = arr2(&[[0., 0.7], [1., 6.5], [0., 17.], [-1., 9.]]);
+        let a2 = arr2(&[[77., 7.2], [15., 5.5], [-9., 16.]]);
+        let expected = directed_hausdorff_view(&a1, &a2, 0);
+        let a1 = a1.mapv(|x| x as f32);
+        let a2 = a2.mapv(|x| x as f32);
+        for workers in 0..9 {
+            let actual = directed_hausdorff_view(&a1, &a2, workers);
+            assert!((actual.distance as f64 - expected.distance).abs() < 1e-5);
+            assert_eq!(actual.ar1_index, expected.ar1_index);
+            assert_eq!(actual.ar2_index, expected.ar2_index);
+        }
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn half_precision_storage() {
+        // f16 storage accumulates in f32 and
+        // returns a single precision distance
+        use half::f16;
+        let a1: Array2<f64> = arr2(&[[0., 0.7], [1., 6.5], [0., 17.], [-1., 9.]]);
+        let a2 = arr2(&[[77., 7.2], [15., 5.5], [-9., 16.]]);
+        let expected = directed_hausdorff_view(&a1, &a2, 0);
+        let a1 = a1.mapv(f16::from_f64);
+        let a2 = a2.mapv(f16::from_f64);
+        for workers in 0..9 {
+            let actual = directed_hausdorff_view(&a1, &a2, workers);
+            let distance: f32 = actual.distance;
+            assert!((distance as f64 - expected.distance).abs() / expected.distance < 1e-2);
+            assert_eq!(actual.ar1_index, expected.ar1_index);
+            assert_eq!(actual.ar2_index, expected.ar2_index);
+        }
+    }
+
+    #[test]
+    fn integer_voxel_coordinates() {
+        // integer coordinates should give the same
+        // result as their floating point equivalents
+        let a1: Array2<f64> = arr2(&[[0., 7., 3.], [1., 65., 2.], [0., 17., 9.], [12., 9., 4.]]);
+        let a2: Array2<f64> = arr2(&[[77., 72., 1.], [15., 55., 0.], [9., 16., 8.]]);
+        let expected = directed_hausdorff_view(&a1, &a2, 0).into_tuple();
+        let a1_u16 = a1.mapv(|x| x as u16);
+        let a2_u16 = a2.mapv(|x| x as u16);
+        let a1_i32 = a1.mapv(|x| -x as i32);
+        let a2_i32 = a2.mapv(|x| -x as i32);
+        for workers in 0..9 {
+            let actual_u16 = directed_hausdorff_view(&a1_u16, &a2_u16, workers);
+            let actual_i32 = directed_hausdorff_view(&a1_i32, &a2_i32, workers);
+            assert_eq!(actual_u16.clone().into_tuple(), expected);
+            assert_eq!(actual_i32.into_tuple(), expected);
+            // the squared distance is exact
+            assert_eq!(actual_u16.reduced_distance, 300);
+        }
+    }
+
+    #[test]
+    fn integer_extremes_do_not_overflow() {
+        // opposite corners of the i32 range in
+        // several dimensions
+        let a1 = Array2::from_elem((1, 4), i32::MIN);
+        let a2 = Array2::from_elem((1, 4), i32::MAX);
+        let side = i32::MAX as f64 - i32::MIN as f64;
+        assert_eq!(
+            directed_hausdorff_view(&a1, &a2, 0).distance,
+            (4. * side * side).sqrt()
+        );
+        let a1 = Array2::from_elem((1, 3), u16::MIN);
+        let a2 = Array2::from_elem((1, 3), u16::MAX);
+        assert_eq!(
+            directed_hausdorff_view(&a1, &a2, 0).distance,
+            (3. * 65535_f64.powi(2)).sqrt()
+        );
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        // bad inputs should be reported, not panic,
+        // for both the serial and parallel paths
+        let a1 = arr2(&[[0., 0.7], [1., 6.5], [0., 17.], [-1., 9.]]);
+        let a2 = arr2(&[[77., 7.2], [15., 5.5], [-9., 16.]]);
+        let a3 = arr2(&[[77., 7.2, 1.], [15., 5.5, 2.]]);
+        let empty = Array2::<f64>::zeros((0, 2));
+        let mut with_nan = a2.clone();
+        with_nan[[2, 1]] = f64::NAN;
+        let mut with_inf = a1.clone();
+        with_inf[[3, 0]] = f64::NEG_INFINITY;
+        for workers in 0..9 {
+            assert_eq!(
+                try_directed_hausdorff(&a1, &a3, workers),
+                Err(HausdorffError::DimensionMismatch {
+                    ar1_columns: 2,
+                    ar2_columns: 3
+                })
+            );
+            assert_eq!(
+                try_directed_hausdorff(&empty, &a2, workers),
+                Err(HausdorffError::EmptyInput { argument: "ar1" })
+            );
+            assert_eq!(
+                try_directed_hausdorff(&a1, &empty, workers),
+                Err(HausdorffError::EmptyInput { argument: "ar2" })
+            );
+            assert_eq!(
+                try_directed_hausdorff(&a1, &with_nan, workers),
+                Err(HausdorffError::NonFinite {
+                    argument: "ar2",
+                    row: 2,
+                    column: 1
+                })
+            );
+            assert_eq!(
+                try_directed_hausdorff(&with_inf, &a2, workers),
+                Err(HausdorffError::NonFinite {
+                    argument: "ar1",
+                    row: 3,
+                    column: 0
+                })
+            );
+            assert_eq!(
+                try_directed_hausdorff(&a1, &a2, workers),
+                Ok(directed_hausdorff_view(&a1, &a2, workers))
+            );
+        }
+    }
+
+    #[test]
+    fn non_finite_policies() {
+        let a1 = arr2(&[[0., 0.7], [1., 6.5], [0., 17.], [-1., 9.]]);
+        let a2 = arr2(&[[77., 7.2], [15., 5.5], [-9., 16.]]);
+        let mut a1_nan = Array2::<f64>::from_elem((6, 2), f64::NAN);
+        a1_nan.slice_mut(s![1..5, ..]).assign(&a1);
+        // a row that is only partially missing
+        a1_nan[[5, 0]] = 200.;
+        let mut a2_nan = Array2::<f64>::zeros((4, 2));
+        a2_nan.slice_mut(s![..3, ..]).assign(&a2);
+        a2_nan[[3, 1]] = f64::INFINITY;
+        let expected = directed_hausdorff_view(&a1, &a2, 0);
+        for workers in 0..9 {
+            let options = HausdorffOptions::new().workers(workers);
+            assert!(try_directed_hausdorff_with(&a1_nan, &a2_nan, &options).is_err());
+            // skipping the non-finite rows matches
+            // removing them, apart from the row offset
+            let options = options.non_finite_policy(NonFinitePolicy::Skip);
+            let actual = try_directed_hausdorff_with(&a1_nan, &a2_nan, &options).unwrap();
+            assert_eq!(actual.distance, expected.distance);
+            assert_eq!(actual.ar1_index, expected.ar1_index + 1);
+            assert_eq!(actual.ar2_index, expected.ar2_index);
+            let actual = try_directed_hausdorff_with(&a1, &a2_nan, &options).unwrap();
+            assert_eq!(actual, expected);
+            assert_eq!(
+                try_directed_hausdorff_with(&a1, a1_nan.slice(s![..1, ..]), &options),
+                Err(HausdorffError::EmptyInput { argument: "ar2" })
+            );
+            let options = options.non_finite_policy(NonFinitePolicy::Propagate);
+            let actual = try_directed_hausdorff_with(&a1_nan, &a2, &options).unwrap();
+            assert!(actual.distance.is_nan());
+            assert_eq!((actual.ar1_index, actual.ar2_index), (0, 0));
+            let actual = try_directed_hausdorff_with(&a1, &a2_nan, &options).unwrap();
+            assert!(actual.distance.is_nan());
+            assert!(actual.reduced_distance.is_nan());
+            assert_eq!((actual.ar1_index, actual.ar2_index), (0, 3));
+            let actual = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ar2 must contain at least one point")]
+    fn empty_input_panics() {
+        let a1 = arr2(&[[0., 0.7], [1., 6.5]]);
+        let empty = Array2::<f64>::zeros((0, 2));
+        directed_hausdorff(Arc::new(a1), Arc::new(empty), 2);
+    }
+
     #[test]
     fn compare_scipy_2d() {
         // this isn't part of the SciPy test suite, but
@@ -473,38 +1092,28 @@ mod scipy_tests {
     // make a `NOTE` in cases where there is a deviation
     // due to the random shuffling/seed in SciPy
     use super::*;
+    use crate::fixtures;
     use ndarray::prelude::*;
-    use ndarray_npy::NpzReader;
-    use std::fs::File;
 
     fn setup_tests() -> (Array2<f64>, Array2<f64>, Array2<f64>, Array2<f64>) {
         // make the exact arrays used in the SciPy test
         // suite available for testing here
-        let mut npz =
-            NpzReader::new(File::open("src/paths.npz").expect("unable to open data file"))
-                .expect("unable to read from file");
-        let path_1: Array2<f64> = npz
-            .by_name("path_1.npy")
-            .expect("unable to retrieve path_1 field");
-        let path_2: Array2<f64> = npz
-            .by_name("path_2.npy")
-            .expect("unable to retrieve path_2 field");
-        let path_1_4d: Array2<f64> = npz
-            .by_name("path_1_4d.npy")
-            .expect("unable to retrieve path_1_4d field");
-        let path_2_4d: Array2<f64> = npz
-            .by_name("path_2_4d.npy")
-            .expect("unable to retrieve path_2_4d field");
-        (path_1, path_2, path_1_4d, path_2_4d)
+        let (path_1, path_2) = fixtures::paths();
+        (
+            path_1,
+            path_2,
+            fixtures::path("path_1_4d"),
+            fixtures::path("path_2_4d"),
+        )
     }
 
     #[test]
     fn test_indices_scipy() {
         // test for a result identical to SciPy test:
         // test_hausdorff.py::TestHausdorff::test_indices
-        let path_simple_1 = Arc::new(arr2(&[[-1., -12.], [0., 0.], [1., 1.], [3., 7.], [1., 2.]]));
-        let path_simple_2 = Arc::new(arr2(&[[0., 0.], [1., 1.], [4., 100.], [10., 9.]]));
-        let expected_result = (93.00537618869137, 2, 3);
+        let (path_simple_1, path_simple_2) = fixtures::indices();
+        let (path_simple_1, path_simple_2) = (Arc::new(path_simple_1), Arc::new(path_simple_2));
+        let expected_result = fixtures::INDICES_REVERSE;
         for workers in 0..9 {
             let actual_result =
                 directed_hausdorff(path_simple_2.clone(), path_simple_1.clone(), workers);
@@ -512,13 +1121,29 @@ mod scipy_tests {
         }
     }
 
+    #[test]
+    fn test_indices_seeded_scipy() {
+        // the SciPy test above uses the default seed=0,
+        // which the SciPy shuffle reproduces exactly
+        let (path_simple_1, path_simple_2) = fixtures::indices();
+        let expected_result = fixtures::INDICES_REVERSE;
+        for workers in 0..9 {
+            let options = HausdorffOptions::new()
+                .workers(workers)
+                .shuffle(Shuffle::SciPy(0));
+            let actual_result =
+                try_directed_hausdorff_with(&path_simple_2, &path_simple_1, &options).unwrap();
+            assert_eq!(actual_result.into_tuple(), expected_result);
+        }
+    }
+
     #[test]
     fn test_symmetry_scipy() {
         // test for a result identical to SciPy test:
         // test_hausdorff.py::TestHausdorff::test_symmetry
         let (path_1, path_2, _, _) = setup_tests();
-        let expected_forward = 1.000681524361451;
-        let expected_reverse = 2.3000000000000003;
+        let expected_forward = fixtures::PATHS_FORWARD;
+        let expected_reverse = fixtures::PATHS_REVERSE;
         let path_1 = Arc::new(path_1);
         let path_2 = Arc::new(path_2);
         for workers in 0..9 {
@@ -529,12 +1154,31 @@ mod scipy_tests {
             assert_eq!(actual_reverse, expected_reverse);
         }
     }
+
+    #[test]
+    fn test_symmetric_scipy() {
+        // the symmetric distance is the max of the directed
+        // distances in test_hausdorff.py::TestHausdorff::test_symmetry
+        let (path_1, path_2, _, _) = setup_tests();
+        for prune in [false, true] {
+            for workers in 0..9 {
+                let options = HausdorffOptions::new()
+                    .workers(workers)
+                    .symmetric_pruning(prune);
+                let actual = try_hausdorff_with(&path_1, &path_2, &options).unwrap();
+                assert_eq!(actual.distance, fixtures::PATHS_REVERSE);
+                assert_eq!(actual.direction, Direction::Reverse);
+                assert_eq!(actual.forward.distance, fixtures::PATHS_FORWARD);
+            }
+        }
+    }
+
     #[test]
     fn test_brute_force_comparison_forward_scipy() {
         // test for a result identical to SciPy test:
         // test_hausdorff.py::TestHausdorff::test_brute_force_comparison_forward
         let (path_1, path_2, _, _) = setup_tests();
-        let expected_forward = 1.000681524361451;
+        let expected_forward = fixtures::PATHS_FORWARD;
         let path_1 = Arc::new(path_1);
         let path_2 = Arc::new(path_2);
         for workers in 0..9 {
@@ -548,7 +1192,7 @@ mod scipy_tests {
         // test for a result identical to SciPy test:
         // test_hausdorff.py::TestHausdorff::test_brute_force_comparison_reverse
         let (path_1, path_2, _, _) = setup_tests();
-        let expected_reverse = 2.3000000000000003;
+        let expected_reverse = fixtures::PATHS_REVERSE;
         let path_1 = Arc::new(path_1);
         let path_2 = Arc::new(path_2);
         for workers in 0..9 {
@@ -577,13 +1221,51 @@ mod scipy_tests {
         let (path_1, path_2, _, _) = setup_tests();
         let path_1 = Arc::new(path_1.slice(s![.., ..2]).to_owned());
         let path_2 = Arc::new(path_2.slice(s![.., ..2]).to_owned());
-        let expected = 1.000681524361451;
+        let expected = fixtures::PATHS_FORWARD;
         for workers in 0..9 {
             let actual = directed_hausdorff(path_1.clone(), path_2.clone(), workers).0;
             assert_eq!(actual, expected);
         }
     }
 
+    #[test]
+    fn test_2d_data_forward_view_scipy() {
+        // same as test_2d_data_forward_scipy, but
+        // borrowing the column subset instead of copying it
+        let (path_1, path_2, _, _) = setup_tests();
+        let expected = fixtures::PATHS_FORWARD;
+        for workers in 0..9 {
+            let actual = directed_hausdorff_view(
+                path_1.slice(s![.., ..2]),
+                path_2.slice(s![.., ..2]),
+                workers,
+            )
+            .distance;
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_single_precision_scipy() {
+        // single precision storage should reproduce
+        // the double precision SciPy results within
+        // single precision tolerance
+        let (path_1, path_2, path_1_4d, path_2_4d) = setup_tests();
+        let cases = [
+            (&path_1, &path_2, fixtures::PATHS_FORWARD),
+            (&path_2, &path_1, fixtures::PATHS_REVERSE),
+            (&path_2_4d, &path_1_4d, 22.119900542271886),
+        ];
+        for (a1, a2, expected) in cases {
+            let a1 = a1.mapv(|x| x as f32);
+            let a2 = a2.mapv(|x| x as f32);
+            for workers in 0..9 {
+                let actual = directed_hausdorff_view(&a1, &a2, workers).distance;
+                assert!((actual as f64 - expected).abs() / expected < 1e-6);
+            }
+        }
+    }
+
     #[test]
     fn test_4d_data_reverse_scipy() {
         // test for a result identical to SciPy test:
@@ -0,0 +1,1078 @@
+//! Distance metrics between pairs of points.
+//!
+//! The early break algorithm only ever compares distances, so each
+//! metric provides a cheaper "reduced distance" that orders pairs of
+//! points the same way as the distance itself, along with the final
+//! transform into the distance, applied once to the result. Squared
+//! Euclidean distances and the deferred square root are the
+//! original example.
+
+use crate::coordinate::{Accumulator, Coordinate, Distance};
+use crate::error::HausdorffError;
+use ndarray::{Array2, ArrayView1, ArrayView2, AsArray, Ix2};
+use num_traits::{Float, NumCast};
+
+/// A distance between points with coordinates of type `T`.
+///
+/// The reduced distance must be a non-negative, monotone increasing
+/// function of the distance, i.e., for any points `a`, `b`, `c`, `d`,
+/// `reduced_distance(a, b) < reduced_distance(c, d)` exactly when
+/// `distance(a, b) < distance(c, d)`. It is accumulated in the
+/// coordinate type's [`Accumulator`].
+///
+/// # Examples
+///
+/// A metric of our own, the Euclidean distance in the
+/// first two columns only (i.e., ignoring elevations):
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// struct Horizontal;
+///
+/// impl Metric<f64> for Horizontal {
+///     fn reduced_distance(&self, a: ArrayView1<f64>, b: ArrayView1<f64>) -> f64 {
+///         (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)
+///     }
+///
+///     fn distance(&self, reduced: f64) -> f64 {
+///         reduced.sqrt()
+///     }
+/// }
+///
+/// let a1 = arr2(&[[0.0, 0.0, 100.0], [3.0, 4.0, 0.0]]);
+/// let a2 = arr2(&[[0.0, 0.0, 0.0]]);
+/// let options = HausdorffOptions::new().metric(Horizontal);
+/// let result = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+/// assert_eq!(result.distance, 5.0);
+/// ```
+pub trait Metric<T: Coordinate>: Sync {
+    /// The reduced distance between the points `a` and `b`.
+    fn reduced_distance(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> T::Accumulator;
+
+    /// The distance corresponding to a reduced distance.
+    fn distance(&self, reduced: T::Accumulator) -> Distance<T>;
+
+    /// Check that the metric applies to points with `columns`
+    /// coordinates, reporting [`HausdorffError::InvalidMetric`]
+    /// otherwise. Any number of columns is accepted by default.
+    fn check_columns(&self, columns: usize) -> Result<(), HausdorffError> {
+        let _ = columns;
+        Ok(())
+    }
+}
+
+/// The Euclidean distance, with the squared distance as the reduced
+/// distance. This is the default metric.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Euclidean;
+
+impl<T: Coordinate> Metric<T> for Euclidean {
+    fn reduced_distance(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> T::Accumulator {
+        (0..a.len()).fold(T::Accumulator::ZERO, |d, k| {
+            d + a[k].squared_difference(b[k])
+        })
+    }
+
+    fn distance(&self, reduced: T::Accumulator) -> Distance<T> {
+        reduced.sqrt()
+    }
+}
+
+/// The Manhattan (taxicab, or L1) distance, the sum of
+/// the absolute differences of the coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Manhattan;
+
+impl<T: Coordinate> Metric<T> for Manhattan {
+    fn reduced_distance(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> T::Accumulator {
+        (0..a.len()).fold(T::Accumulator::ZERO, |d, k| d + a[k].abs_difference(b[k]))
+    }
+
+    fn distance(&self, reduced: T::Accumulator) -> Distance<T> {
+        reduced.to_distance()
+    }
+}
+
+/// The Chebyshev (chessboard, or L∞) distance, the largest
+/// of the absolute differences of the coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Chebyshev;
+
+impl<T: Coordinate> Metric<T> for Chebyshev {
+    fn reduced_distance(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> T::Accumulator {
+        (0..a.len()).fold(T::Accumulator::ZERO, |d, k| {
+            let diff = a[k].abs_difference(b[k]);
+            if diff > d {
+                diff
+            } else {
+                d
+            }
+        })
+    }
+
+    fn distance(&self, reduced: T::Accumulator) -> Distance<T> {
+        reduced.to_distance()
+    }
+}
+
+/// The Minkowski (Lp) distance for `p >= 1`, with the sum of the
+/// `p`-th powers of the absolute differences as the reduced distance.
+///
+/// Available for floating point coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Minkowski {
+    p: f64,
+}
+
+impl Minkowski {
+    /// The Minkowski distance of order `p`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is less than one (or NaN), for which the
+    /// triangle inequality does not hold, or infinite, for which
+    /// [`Chebyshev`] is the distance.
+    pub fn new(p: f64) -> Self {
+        assert!(
+            (1.0..f64::INFINITY).contains(&p),
+            "the Minkowski order must be finite and at least 1, not {}",
+            p
+        );
+        Minkowski { p }
+    }
+
+    /// The order of the distance.
+    pub fn p(&self) -> f64 {
+        self.p
+    }
+}
+
+impl<T, A> Metric<T> for Minkowski
+where
+    T: Coordinate<Accumulator = A>,
+    A: Accumulator<Distance = A> + Float,
+{
+    fn reduced_distance(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> A {
+        let p: A = NumCast::from(self.p).expect("the order is finite");
+        (0..a.len()).fold(A::ZERO, |d, k| d + a[k].abs_difference(b[k]).powf(p))
+    }
+
+    fn distance(&self, reduced: A) -> A {
+        let p: A = NumCast::from(self.p).expect("the order is finite");
+        reduced.powf(p.recip())
+    }
+}
+
+/// The weighted Euclidean distance, with a non-negative
+/// weight for the squared difference of each coordinate.
+///
+/// Available for floating point coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightedEuclidean {
+    weights: Vec<f64>,
+}
+
+impl WeightedEuclidean {
+    /// The weighted Euclidean distance with the
+    /// given weight for each column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any weight is negative or not finite.
+    pub fn new(weights: impl Into<Vec<f64>>) -> Self {
+        let weights = weights.into();
+        assert!(
+            weights.iter().all(|w| w.is_finite() && *w >= 0.0),
+            "the weights must be finite and non-negative"
+        );
+        WeightedEuclidean { weights }
+    }
+
+    /// The weight of each column.
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+}
+
+impl<T, A> Metric<T> for WeightedEuclidean
+where
+    T: Coordinate<Accumulator = A>,
+    A: Accumulator<Distance = A> + Float,
+{
+    fn reduced_distance(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> A {
+        (0..a.len()).fold(A::ZERO, |d, k| {
+            let w: A = NumCast::from(self.weights[k]).expect("the weights are finite");
+            d + w * a[k].squared_difference(b[k])
+        })
+    }
+
+    fn distance(&self, reduced: A) -> A {
+        Accumulator::sqrt(reduced)
+    }
+
+    fn check_columns(&self, columns: usize) -> Result<(), HausdorffError> {
+        check_length("weights", self.weights.len(), columns)
+    }
+}
+
+/// Check that a metric has one of its `values` for each column.
+fn check_length(values: &str, len: usize, columns: usize) -> Result<(), HausdorffError> {
+    if len == columns {
+        Ok(())
+    } else {
+        Err(HausdorffError::InvalidMetric(format!(
+            "{} {} for points with {} columns",
+            len, values, columns
+        )))
+    }
+}
+
+/// The Euclidean distance between points on a grid with the given
+/// spacing along each axis, e.g., voxel centres in millimetres, as if
+/// each column had been scaled by its spacing beforehand.
+///
+/// Available for floating point coordinates, since scaled distances
+/// between integer coordinates are not exact. Integer voxel indices
+/// convert exactly to `f64` (or, up to 24 bits, to `f32`).
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// // voxel indices of two surfaces, with 0.7 x 0.7 x 3 mm voxels
+/// let a1 = arr2(&[[10_u16, 20, 5], [11, 20, 6]]).mapv(f64::from);
+/// let a2 = arr2(&[[10_u16, 20, 5]]).mapv(f64::from);
+/// let options = HausdorffOptions::new().metric(Spacing::new([0.7, 0.7, 3.0]));
+/// let result = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+/// assert_eq!(result.distance, (0.7_f64.powi(2) + 3.0_f64.powi(2)).sqrt());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spacing {
+    spacing: Vec<f64>,
+}
+
+impl Spacing {
+    /// The Euclidean distance with the given
+    /// spacing along each column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any spacing is not positive and finite.
+    pub fn new(spacing: impl Into<Vec<f64>>) -> Self {
+        let spacing = spacing.into();
+        assert!(
+            spacing.iter().all(|s| s.is_finite() && *s > 0.0),
+            "the spacing must be positive and finite"
+        );
+        Spacing { spacing }
+    }
+
+    /// The spacing along each column.
+    pub fn spacing(&self) -> &[f64] {
+        &self.spacing
+    }
+}
+
+impl<T, A> Metric<T> for Spacing
+where
+    T: Coordinate<Accumulator = A>,
+    A: Accumulator<Distance = A> + Float,
+{
+    fn reduced_distance(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> A {
+        (0..a.len()).fold(A::ZERO, |d, k| {
+            let s: A = NumCast::from(self.spacing[k]).expect("the spacing is finite");
+            let diff = s * a[k].abs_difference(b[k]);
+            d + diff * diff
+        })
+    }
+
+    fn distance(&self, reduced: A) -> A {
+        Accumulator::sqrt(reduced)
+    }
+
+    fn check_columns(&self, columns: usize) -> Result<(), HausdorffError> {
+        check_length("spacings", self.spacing.len(), columns)
+    }
+}
+
+/// The Mahalanobis distance, `sqrt((a - b)ᵀ P (a - b))` for the
+/// inverse `P` of a (positive definite) covariance matrix.
+///
+/// The distance is calculated as `|Lᵀ (a - b)|` from the Cholesky
+/// factor `L` of `P = L Lᵀ`, without scaling a copy of either array,
+/// with the product in `f64`.
+///
+/// Available for floating point coordinates.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// // correlated features, with the covariance [[4, 2], [2, 2]]
+/// let inverse_covariance = arr2(&[[0.5, -0.5], [-0.5, 1.0]]);
+/// let metric = Mahalanobis::from_inverse_covariance(&inverse_covariance).unwrap();
+/// let a1 = arr2(&[[2.0_f64, 1.0], [-1.0, 1.0]]);
+/// let a2 = arr2(&[[0.0, 0.0]]);
+/// let options = HausdorffOptions::new().metric(metric);
+/// let result = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+/// // against the correlation, (-1, 1) is the farther point
+/// assert_eq!(result.ar1_index, 1);
+/// assert!((result.distance - 2.5_f64.sqrt()).abs() < 1e-12);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mahalanobis {
+    // the transpose of the Cholesky factor, in standard layout
+    upper: Array2<f64>,
+}
+
+impl Mahalanobis {
+    /// The Mahalanobis distance for the given inverse covariance
+    /// matrix. As for LAPACK, only its lower triangle is read.
+    ///
+    /// Reports [`HausdorffError::InvalidMetric`] if the matrix is
+    /// not square or not positive definite.
+    pub fn from_inverse_covariance<'a, V>(inverse_covariance: V) -> Result<Self, HausdorffError>
+    where
+        V: AsArray<'a, f64, Ix2>,
+    {
+        let p: ArrayView2<f64> = inverse_covariance.into();
+        check_square(&p)?;
+        let n = p.nrows();
+        let mut l = Array2::<f64>::zeros((n, n));
+        for j in 0..n {
+            let diagonal = p[[j, j]] - (0..j).map(|k| l[[j, k]].powi(2)).sum::<f64>();
+            // also rejects NaN
+            if !(diagonal > 0.0 && diagonal.is_finite()) {
+                return Err(HausdorffError::InvalidMetric(
+                    "the inverse covariance is not positive definite".to_string(),
+                ));
+            }
+            l[[j, j]] = diagonal.sqrt();
+            for i in j + 1..n {
+                let dot = (0..j).map(|k| l[[i, k]] * l[[j, k]]).sum::<f64>();
+                l[[i, j]] = (p[[i, j]] - dot) / l[[j, j]];
+            }
+        }
+        Mahalanobis::from_cholesky(&l)
+    }
+
+    /// The Mahalanobis distance for the inverse covariance `L Lᵀ`,
+    /// given its lower triangular Cholesky factor `L`.
+    ///
+    /// Reports [`HausdorffError::InvalidMetric`] if the factor is
+    /// not square and lower triangular, with a positive diagonal.
+    pub fn from_cholesky<'a, V>(factor: V) -> Result<Self, HausdorffError>
+    where
+        V: AsArray<'a, f64, Ix2>,
+    {
+        let l: ArrayView2<f64> = factor.into();
+        check_square(&l)?;
+        let invalid = |reason: &str| Err(HausdorffError::InvalidMetric(reason.to_string()));
+        if l.iter().any(|x| !x.is_finite()) {
+            return invalid("the Cholesky factor is not finite");
+        }
+        if l.indexed_iter().any(|((i, j), &x)| j > i && x != 0.0) {
+            return invalid("the Cholesky factor is not lower triangular");
+        }
+        if l.diag().iter().any(|&x| x <= 0.0) {
+            return invalid("the Cholesky factor has a non-positive diagonal");
+        }
+        Ok(Mahalanobis {
+            upper: l.t().as_standard_layout().into_owned(),
+        })
+    }
+
+    /// The lower triangular Cholesky factor `L`
+    /// of the inverse covariance `L Lᵀ`.
+    pub fn cholesky_factor(&self) -> ArrayView2<'_, f64> {
+        self.upper.t()
+    }
+}
+
+fn check_square(matrix: &ArrayView2<f64>) -> Result<(), HausdorffError> {
+    if matrix.is_square() {
+        Ok(())
+    } else {
+        Err(HausdorffError::InvalidMetric(format!(
+            "a {}x{} matrix is not square",
+            matrix.nrows(),
+            matrix.ncols()
+        )))
+    }
+}
+
+impl<T, A> Metric<T> for Mahalanobis
+where
+    T: Coordinate<Accumulator = A> + Into<A>,
+    A: Accumulator<Distance = A> + Float,
+{
+    fn reduced_distance(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> A {
+        // the differences, on the stack unless there are many columns
+        let n = a.len();
+        let mut stack = [0.0; 16];
+        let mut heap = Vec::new();
+        let diff = if n <= stack.len() {
+            &mut stack[..n]
+        } else {
+            heap.resize(n, 0.0);
+            &mut heap[..]
+        };
+        for (k, d) in diff.iter_mut().enumerate() {
+            *d = (a[k].into() - b[k].into())
+                .to_f64()
+                .expect("a float difference");
+        }
+        // the squared length of Lᵀ (a - b)
+        let squared = self
+            .upper
+            .rows()
+            .into_iter()
+            .enumerate()
+            .fold(0.0, |d, (r, row)| {
+                let row = row.as_slice().expect("the factor is in standard layout");
+                let component: f64 = row[r..].iter().zip(&diff[r..]).map(|(u, x)| u * x).sum();
+                d + component * component
+            });
+        NumCast::from(squared).expect("a finite distance")
+    }
+
+    fn distance(&self, reduced: A) -> A {
+        Accumulator::sqrt(reduced)
+    }
+
+    fn check_columns(&self, columns: usize) -> Result<(), HausdorffError> {
+        if self.upper.nrows() == columns {
+            Ok(())
+        } else {
+            Err(HausdorffError::InvalidMetric(format!(
+                "a {}x{} matrix for points with {} columns",
+                self.upper.nrows(),
+                self.upper.ncols(),
+                columns
+            )))
+        }
+    }
+}
+
+/// The Euclidean distance between particles in a periodic simulation
+/// cell, by the minimum image convention, i.e., the distance to the
+/// nearest periodic image of the other particle.
+///
+/// The cell is either orthorhombic, given by its lengths along each
+/// axis, or triclinic, given by a matrix whose rows are the lattice
+/// vectors (the convention of e.g. ASE and MDAnalysis). Particles
+/// may lie outside the cell, and the witness points are reported
+/// as given rather than as their images.
+///
+/// Available for floating point coordinates.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// // particles either side of the edge of a 10 x 10 x 10 box
+/// let a1 = arr2(&[[0.5_f64, 5.0, 5.0], [5.0, 5.0, 5.0]]);
+/// let a2 = arr2(&[[9.5, 5.0, 5.0], [6.0, 5.0, 5.0]]);
+/// let options = HausdorffOptions::new().metric(PeriodicBox::orthorhombic([10.0; 3]));
+/// let result = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+/// assert_eq!(result.distance, 1.0);
+///
+/// // a hexagonal cell, for which (9, 0) is 1 from the image (10, 0)
+/// let cell = arr2(&[[10.0, 0.0], [5.0, 8.660254037844386]]);
+/// let a1 = arr2(&[[9.0_f64, 0.0]]);
+/// let a2 = arr2(&[[0.0, 0.0]]);
+/// let options = HausdorffOptions::new().metric(PeriodicBox::triclinic(&cell).unwrap());
+/// let result = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+/// assert!((result.distance - 1.0).abs() < 1e-12);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeriodicBox {
+    cell: Cell,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Cell {
+    Orthorhombic(Vec<f64>),
+    // with up to three lattice vectors, so that
+    // differences need no allocation
+    Triclinic {
+        dim: usize,
+        cell: [[f64; 3]; 3],
+        inverse: [[f64; 3]; 3],
+    },
+}
+
+impl PeriodicBox {
+    /// An orthorhombic cell with the given length along each axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any length is not positive and finite.
+    pub fn orthorhombic(lengths: impl Into<Vec<f64>>) -> Self {
+        let lengths = lengths.into();
+        assert!(
+            lengths.iter().all(|l| l.is_finite() && *l > 0.0),
+            "the box lengths must be positive and finite"
+        );
+        PeriodicBox {
+            cell: Cell::Orthorhombic(lengths),
+        }
+    }
+
+    /// A triclinic cell, given by a (square) matrix with the lattice
+    /// vectors as its rows, for points with up to three columns.
+    ///
+    /// The minimum image is found among the images neighbouring the
+    /// difference wrapped into the cell, which is exact unless the
+    /// cell is extremely skewed (it is for any reduced cell).
+    ///
+    /// Reports [`HausdorffError::InvalidMetric`] if the matrix is
+    /// not square, has more than three rows or is singular.
+    pub fn triclinic<'a, V>(cell: V) -> Result<Self, HausdorffError>
+    where
+        V: AsArray<'a, f64, Ix2>,
+    {
+        let h: ArrayView2<f64> = cell.into();
+        check_square(&h)?;
+        if h.iter().any(|x| !x.is_finite()) {
+            return Err(HausdorffError::InvalidMetric(
+                "the cell matrix is not finite".to_string(),
+            ));
+        }
+        let dim = h.nrows();
+        if dim > 3 {
+            return Err(HausdorffError::InvalidMetric(format!(
+                "triclinic cells have up to 3 lattice vectors, not {}",
+                dim
+            )));
+        }
+        let mut cell = [[0.0; 3]; 3];
+        for ((i, j), &x) in h.indexed_iter() {
+            cell[i][j] = x;
+        }
+        let inverse = invert(&cell, dim).ok_or_else(|| {
+            HausdorffError::InvalidMetric("the cell matrix is singular".to_string())
+        })?;
+        Ok(PeriodicBox {
+            cell: Cell::Triclinic { dim, cell, inverse },
+        })
+    }
+}
+
+/// Invert the leading `dim` by `dim` block of a matrix by Gauss-Jordan
+/// elimination with partial pivoting, unless it is singular.
+fn invert(matrix: &[[f64; 3]; 3], dim: usize) -> Option<[[f64; 3]; 3]> {
+    let mut m = *matrix;
+    let mut inverse = [[0.0; 3]; 3];
+    for (i, row) in inverse.iter_mut().enumerate().take(dim) {
+        row[i] = 1.0;
+    }
+    for col in 0..dim {
+        let pivot = (col..dim).max_by(|&i, &j| m[i][col].abs().total_cmp(&m[j][col].abs()))?;
+        if m[pivot][col] == 0.0 {
+            return None;
+        }
+        m.swap(col, pivot);
+        inverse.swap(col, pivot);
+        let scale = m[col][col];
+        for k in 0..dim {
+            m[col][k] /= scale;
+            inverse[col][k] /= scale;
+        }
+        for row in 0..dim {
+            if row != col {
+                let factor = m[row][col];
+                for k in 0..dim {
+                    m[row][k] -= factor * m[col][k];
+                    inverse[row][k] -= factor * inverse[col][k];
+                }
+            }
+        }
+    }
+    let finite = inverse.iter().flatten().all(|x| x.is_finite());
+    finite.then_some(inverse)
+}
+
+impl<T, A> Metric<T> for PeriodicBox
+where
+    T: Coordinate<Accumulator = A> + Into<A>,
+    A: Accumulator<Distance = A> + Float,
+{
+    fn reduced_distance(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> A {
+        let cast = |x: f64| -> A { NumCast::from(x).expect("the cell is finite") };
+        match &self.cell {
+            Cell::Orthorhombic(lengths) => (0..a.len()).fold(A::ZERO, |d, k| {
+                let length = cast(lengths[k]);
+                let diff = a[k].into() - b[k].into();
+                let diff = diff - length * (diff / length).round();
+                d + diff * diff
+            }),
+            &Cell::Triclinic {
+                dim,
+                ref cell,
+                ref inverse,
+            } => {
+                // the fractional difference, wrapped into [-1/2, 1/2]
+                let mut diff = [A::zero(); 3];
+                for k in 0..dim {
+                    diff[k] = a[k].into() - b[k].into();
+                }
+                let mut fractional = [A::zero(); 3];
+                for (j, s) in fractional.iter_mut().enumerate().take(dim) {
+                    let x = (0..dim).fold(A::zero(), |s, k| s + diff[k] * cast(inverse[k][j]));
+                    *s = x - x.round();
+                }
+                // the nearest of the neighbouring images
+                let mut nearest = A::MAX;
+                for image in 0..3_usize.pow(dim as u32) {
+                    let mut shift = image;
+                    let mut offsets = [A::zero(); 3];
+                    for offset in offsets.iter_mut().take(dim) {
+                        *offset = cast((shift % 3) as f64 - 1.0);
+                        shift /= 3;
+                    }
+                    let d = (0..dim).fold(A::zero(), |d, k| {
+                        let x = (0..dim).fold(A::zero(), |x, j| {
+                            x + (fractional[j] + offsets[j]) * cast(cell[j][k])
+                        });
+                        d + x * x
+                    });
+                    if d < nearest {
+                        nearest = d;
+                    }
+                }
+                nearest
+            }
+        }
+    }
+
+    fn distance(&self, reduced: A) -> A {
+        Accumulator::sqrt(reduced)
+    }
+
+    fn check_columns(&self, columns: usize) -> Result<(), HausdorffError> {
+        match &self.cell {
+            Cell::Orthorhombic(lengths) => check_length("box lengths", lengths.len(), columns),
+            Cell::Triclinic { dim, .. } => check_length("lattice vectors", *dim, columns),
+        }
+    }
+}
+
+/// The unit of angular coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AngleUnit {
+    /// Degrees, the usual unit of GPS coordinates.
+    #[default]
+    Degrees,
+    /// Radians.
+    Radians,
+}
+
+/// The great-circle distance between points given as `[latitude,
+/// longitude]` rows, by the haversine formula on a sphere.
+///
+/// The reduced distance is the haversine of the central angle,
+/// `sin²(Δφ/2) + cos φ1 cos φ2 sin²(Δλ/2)`, which increases with the
+/// distance up to antipodal points, so only the final distance needs
+/// the inverse sine. The distance is in the units of the radius,
+/// by default metres on a sphere with the mean radius of the Earth.
+///
+/// Available for floating point coordinates.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// // points on the 60th parallel, a degree of longitude apart
+/// let a1 = arr2(&[[60.0_f64, 10.0], [60.0, 11.0]]);
+/// let a2 = arr2(&[[60.0, 12.0]]);
+/// let options = HausdorffOptions::new().metric(Haversine::new());
+/// let result = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+/// assert_eq!(result.ar1_index, 0);
+/// assert!((result.distance - 111_190.8).abs() < 0.1);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Haversine {
+    radius: f64,
+    unit: AngleUnit,
+}
+
+impl Default for Haversine {
+    fn default() -> Self {
+        Haversine {
+            radius: Haversine::EARTH_RADIUS,
+            unit: AngleUnit::Degrees,
+        }
+    }
+}
+
+impl Haversine {
+    /// The mean radius of the Earth, in metres.
+    pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+    /// Distances in metres on the Earth, between
+    /// coordinates in degrees.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The radius of the sphere, in the unit of the distances.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the radius is not positive and finite.
+    pub fn radius(mut self, radius: f64) -> Self {
+        assert!(
+            radius > 0.0 && radius.is_finite(),
+            "the radius must be positive and finite, not {}",
+            radius
+        );
+        self.radius = radius;
+        self
+    }
+
+    /// The unit of the latitudes and longitudes.
+    pub fn unit(mut self, unit: AngleUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl<T, A> Metric<T> for Haversine
+where
+    T: Coordinate<Accumulator = A> + Into<A>,
+    A: Accumulator<Distance = A> + Float,
+{
+    fn reduced_distance(&self, a: ArrayView1<T>, b: ArrayView1<T>) -> A {
+        let scale: A = match self.unit {
+            AngleUnit::Degrees => NumCast::from(std::f64::consts::PI / 360.0),
+            AngleUnit::Radians => NumCast::from(0.5),
+        }
+        .expect("a representable scale");
+        let (lat1, lat2): (A, A) = (a[0].into(), b[0].into());
+        let (lon1, lon2): (A, A) = (a[1].into(), b[1].into());
+        // halved angles, so that e.g. cos(2 * half_lat1) = cos(lat1)
+        let half_lat1 = lat1 * scale;
+        let half_lat2 = lat2 * scale;
+        let half_dlat = half_lat1 - half_lat2;
+        let half_dlon = (lon1 - lon2) * scale;
+        let cos_lat1 = (half_lat1 + half_lat1).cos();
+        let cos_lat2 = (half_lat2 + half_lat2).cos();
+        let h = half_dlat.sin().powi(2) + cos_lat1 * cos_lat2 * half_dlon.sin().powi(2);
+        // rounding may stray just outside of [0, 1]
+        h.max(A::zero()).min(A::one())
+    }
+
+    fn distance(&self, reduced: A) -> A {
+        let diameter: A = NumCast::from(2.0 * self.radius).expect("a representable radius");
+        diameter * Float::sqrt(reduced).asin()
+    }
+
+    fn check_columns(&self, columns: usize) -> Result<(), HausdorffError> {
+        if columns == 2 {
+            Ok(())
+        } else {
+            Err(HausdorffError::InvalidMetric(format!(
+                "haversine distances need [latitude, longitude] points, not {} columns",
+                columns
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HausdorffOptions;
+    use ndarray::prelude::*;
+
+    #[test]
+    fn built_in_metrics() {
+        let a = arr1(&[1.0, -2.0, 3.0]);
+        let b = arr1(&[4.0, 2.0, 3.0]);
+        let distance =
+            |metric: &dyn Metric<f64>| metric.distance(metric.reduced_distance(a.view(), b.view()));
+        assert_eq!(distance(&Euclidean), 5.0);
+        assert_eq!(distance(&Manhattan), 7.0);
+        assert_eq!(distance(&Chebyshev), 4.0);
+        assert_eq!(distance(&Minkowski::new(1.0)), 7.0);
+        assert!((distance(&Minkowski::new(2.0)) - 5.0).abs() < 1e-12);
+        assert!((distance(&Minkowski::new(3.0)) - 91.0_f64.cbrt()).abs() < 1e-12);
+        assert_eq!(distance(&WeightedEuclidean::new([1.0, 0.0, 7.0])), 3.0);
+        // integer coordinates have exact reduced distances
+        let a = arr1(&[0_i32, i32::MIN]);
+        let b = arr1(&[3_i32, i32::MAX]);
+        assert_eq!(
+            Manhattan.reduced_distance(a.view(), b.view()),
+            3 + u32::MAX as u128
+        );
+        assert_eq!(
+            Chebyshev.reduced_distance(a.view(), b.view()),
+            u32::MAX as u128
+        );
+    }
+
+    #[test]
+    fn scaled_distances() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(16);
+        let a1 = Array2::from_shape_fn((60, 3), |_| rng.gen_range(0..20) as f64);
+        let a2 = Array2::from_shape_fn((40, 3), |_| rng.gen_range(0..20) as f64);
+        // the same as scaling copies of the arrays
+        let spacing = arr1(&[0.7, 0.7, 3.0]);
+        let (b1, b2) = (&a1 * &spacing, &a2 * &spacing);
+        for workers in [1, 3] {
+            let options = HausdorffOptions::new().workers(workers);
+            let expected = crate::try_hausdorff_with(&b1, &b2, &options).unwrap();
+            let options = options.metric(Spacing::new(spacing.to_vec()));
+            let actual = crate::try_hausdorff_with(&a1, &a2, &options).unwrap();
+            assert_eq!(actual.forward.ar1_index, expected.forward.ar1_index);
+            assert!((actual.distance - expected.distance).abs() < 1e-12);
+        }
+        // a diagonal inverse covariance scales the axes
+        let diagonal = Array2::from_diag(&spacing.mapv(|s| s * s));
+        let metric = Mahalanobis::from_inverse_covariance(&diagonal).unwrap();
+        assert_eq!(metric.cholesky_factor(), Array2::from_diag(&spacing));
+        for (x, y) in a1.rows().into_iter().zip(a2.rows()) {
+            let expected: f64 = Spacing::new(spacing.to_vec()).reduced_distance(x, y);
+            assert!((metric.reduced_distance(x, y) - expected).abs() < 1e-9);
+        }
+        // and a full one matches (a - b)ᵀ P (a - b)
+        let p = arr2(&[[2.0, 0.5, -0.3], [0.5, 1.0, 0.2], [-0.3, 0.2, 0.5]]);
+        let metric = Mahalanobis::from_inverse_covariance(&p).unwrap();
+        let l = metric.cholesky_factor();
+        assert!((l.dot(&l.t()) - &p).iter().all(|x| x.abs() < 1e-12));
+        assert_eq!(Mahalanobis::from_cholesky(l), Ok(metric.clone()));
+        for (x, y) in a1.rows().into_iter().zip(a2.rows()) {
+            let diff = &x - &y;
+            let expected = diff.dot(&p.dot(&diff));
+            let actual: f64 = metric.reduced_distance(x, y);
+            assert!((actual - expected).abs() < 1e-9 * expected.max(1.0));
+        }
+        // with more columns than fit on the stack
+        let identity = Mahalanobis::from_cholesky(&Array2::eye(20)).unwrap();
+        let (x, y) = (Array1::range(0.0, 20.0, 1.0), Array1::<f64>::zeros(20));
+        assert_eq!(identity.reduced_distance(x.view(), y.view()), 2470.0);
+        let serial = HausdorffOptions::new().workers(1).metric(metric);
+        let expected = crate::try_hausdorff_with(&a1, &a2, &serial).unwrap();
+        for workers in [0, 2, 5] {
+            let options = serial.clone().workers(workers);
+            assert_eq!(
+                crate::try_hausdorff_with(&a1, &a2, &options),
+                Ok(expected.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_matrices() {
+        let invalid = |reason: &str| HausdorffError::InvalidMetric(reason.to_string());
+        assert_eq!(
+            Mahalanobis::from_inverse_covariance(Array2::eye(3).slice(s![.., ..2])),
+            Err(invalid("a 3x2 matrix is not square"))
+        );
+        assert_eq!(
+            Mahalanobis::from_inverse_covariance(&arr2(&[[1.0, 2.0], [2.0, 1.0]])),
+            Err(invalid("the inverse covariance is not positive definite"))
+        );
+        assert_eq!(
+            Mahalanobis::from_cholesky(&arr2(&[[1.0, 2.0], [0.0, 1.0]])),
+            Err(invalid("the Cholesky factor is not lower triangular"))
+        );
+        assert_eq!(
+            Mahalanobis::from_cholesky(&arr2(&[[1.0, 0.0], [2.0, 0.0]])),
+            Err(invalid("the Cholesky factor has a non-positive diagonal"))
+        );
+        assert_eq!(
+            Mahalanobis::from_cholesky(&arr2(&[[f64::NAN]])),
+            Err(invalid("the Cholesky factor is not finite"))
+        );
+        let metric = Mahalanobis::from_cholesky(&Array2::eye(2)).unwrap();
+        assert_eq!(
+            Metric::<f64>::check_columns(&metric, 3),
+            Err(invalid("a 2x2 matrix for points with 3 columns"))
+        );
+        assert_eq!(
+            Metric::<f64>::check_columns(&Spacing::new([1.0, 2.0]), 3),
+            Err(invalid("2 spacings for points with 3 columns"))
+        );
+        assert!(std::panic::catch_unwind(|| Spacing::new([1.0, 0.0])).is_err());
+    }
+
+    #[test]
+    fn periodic_distances() {
+        use rand::{Rng, SeedableRng};
+        // the minimum image, by brute force over the images
+        // with up to six cells of shift along each lattice vector
+        fn brute_force(cell: &Array2<f64>, x: ArrayView1<f64>, y: ArrayView1<f64>) -> f64 {
+            let dim = cell.nrows();
+            (0..13_usize.pow(dim as u32))
+                .map(|image| {
+                    let shift = Array1::from_shape_fn(dim, |j| {
+                        (image / 13_usize.pow(j as u32) % 13) as f64 - 6.0
+                    });
+                    let diff = &x - &y + shift.dot(cell);
+                    diff.dot(&diff)
+                })
+                .fold(f64::INFINITY, f64::min)
+        }
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(17);
+        let lengths = [10.0, 12.0, 7.0];
+        let orthorhombic = PeriodicBox::orthorhombic(lengths);
+        let diagonal = PeriodicBox::triclinic(&Array2::from_diag(&arr1(&lengths))).unwrap();
+        let cell = arr2(&[[10.0, 0.0, 0.0], [3.0, 9.0, 0.0], [-2.0, 4.0, 8.0]]);
+        let triclinic = PeriodicBox::triclinic(&cell).unwrap();
+        // including points well outside of the cells
+        let a1 = Array2::from_shape_fn((60, 3), |_| rng.gen_range(-15.0..25.0));
+        let a2 = Array2::from_shape_fn((40, 3), |_| rng.gen_range(-15.0..25.0));
+        for (x, y) in a1.rows().into_iter().zip(a2.rows()) {
+            let expected = brute_force(&Array2::from_diag(&arr1(&lengths)), x, y);
+            let actual: f64 = orthorhombic.reduced_distance(x, y);
+            assert!((actual - expected).abs() < 1e-9);
+            let actual: f64 = diagonal.reduced_distance(x, y);
+            assert!((actual - expected).abs() < 1e-9);
+            let actual: f64 = triclinic.reduced_distance(x, y);
+            assert!((actual - brute_force(&cell, x, y)).abs() < 1e-9);
+        }
+        for metric in [orthorhombic, triclinic] {
+            let serial = HausdorffOptions::new().workers(1).metric(metric);
+            let expected = crate::try_hausdorff_with(&a1, &a2, &serial).unwrap();
+            // wrapping the points into the cell changes nothing
+            let shifted = &a1 + &arr1(&[10.0, 0.0, 0.0]);
+            let wrapped = crate::try_hausdorff_with(&shifted, &a2, &serial).unwrap();
+            assert!((wrapped.distance - expected.distance).abs() < 1e-9);
+            for workers in [0, 2, 5] {
+                let options = serial.clone().workers(workers);
+                assert_eq!(
+                    crate::try_hausdorff_with(&a1, &a2, &options),
+                    Ok(expected.clone())
+                );
+            }
+        }
+        // one and two dimensional cells
+        let line = PeriodicBox::triclinic(&arr2(&[[4.0]])).unwrap();
+        let actual: f64 = line.reduced_distance(arr1(&[0.5]).view(), arr1(&[3.5]).view());
+        assert_eq!(actual, 1.0);
+        let plane = PeriodicBox::triclinic(&arr2(&[[4.0, 0.0], [2.0, 3.0]])).unwrap();
+        let actual: f64 =
+            plane.reduced_distance(arr1(&[0.0, 0.0]).view(), arr1(&[5.5, 3.0]).view());
+        assert_eq!(actual, 0.25);
+    }
+
+    #[test]
+    fn invalid_cells() {
+        let invalid = |reason: &str| HausdorffError::InvalidMetric(reason.to_string());
+        assert_eq!(
+            PeriodicBox::triclinic(&Array2::eye(4)),
+            Err(invalid(
+                "triclinic cells have up to 3 lattice vectors, not 4"
+            ))
+        );
+        assert_eq!(
+            PeriodicBox::triclinic(&arr2(&[[1.0, 2.0], [2.0, 4.0]])),
+            Err(invalid("the cell matrix is singular"))
+        );
+        assert_eq!(
+            PeriodicBox::triclinic(&arr2(&[[f64::INFINITY]])),
+            Err(invalid("the cell matrix is not finite"))
+        );
+        assert_eq!(
+            PeriodicBox::triclinic(&Array2::zeros((2, 3))),
+            Err(invalid("a 2x3 matrix is not square"))
+        );
+        let metric = PeriodicBox::triclinic(&Array2::eye(2)).unwrap();
+        assert_eq!(
+            Metric::<f64>::check_columns(&metric, 3),
+            Err(invalid("2 lattice vectors for points with 3 columns"))
+        );
+        assert!(std::panic::catch_unwind(|| PeriodicBox::orthorhombic([1.0, -1.0])).is_err());
+    }
+
+    #[test]
+    fn haversine_distances() {
+        let distance = |metric: Haversine, a: [f64; 2], b: [f64; 2]| {
+            let (a, b) = (arr1(&a), arr1(&b));
+            Metric::<f64>::distance(&metric, metric.reduced_distance(a.view(), b.view()))
+        };
+        let earth = Haversine::new();
+        // Big Ben to the Eiffel Tower
+        let actual = distance(earth, [51.5007, -0.1246], [48.8584, 2.2945]);
+        assert!((actual - 340_539.39).abs() < 0.01);
+        // a degree apart across the antimeridian, and two over the pole
+        let degree = Haversine::EARTH_RADIUS * std::f64::consts::PI / 180.0;
+        let actual = distance(earth, [0.0, 179.5], [0.0, -179.5]);
+        assert!((actual - degree).abs() < 1e-6);
+        let actual = distance(earth, [89.0, 0.0], [89.0, 180.0]);
+        assert!((actual - 2.0 * degree).abs() < 1e-6);
+        // antipodes, where rounding must not leave the domain of asin
+        let actual = distance(earth.radius(1.0), [30.0, 20.0], [-30.0, -160.0]);
+        assert!((actual - std::f64::consts::PI).abs() < 1e-12);
+        let radians = earth.radius(1.0).unit(AngleUnit::Radians);
+        let actual = distance(radians, [0.1, 0.2], [0.1, 0.2 + 1e-3]);
+        assert!((actual - 1e-3 * 0.1_f64.cos()).abs() < 1e-9);
+        // the reduced distances order points like the distances
+        let a = arr2(&[[10.0, 20.0], [10.5, 19.0], [-80.0, 150.0], [45.0, -170.0]]);
+        let mut pairs = Vec::new();
+        for x in a.rows() {
+            for y in a.rows() {
+                pairs.push((
+                    earth.reduced_distance(x, y),
+                    distance(earth, [x[0], x[1]], [y[0], y[1]]),
+                ));
+            }
+        }
+        pairs.sort_by(|p, q| p.0.partial_cmp(&q.0).unwrap());
+        assert!(pairs.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert!(Metric::<f32>::check_columns(&earth, 3).is_err());
+    }
+
+    #[test]
+    fn haversine_trajectories() {
+        // near the pole, where a planar projection is badly distorted
+        let a1 = arr2(&[[80.0_f64, 0.0], [80.0, 90.0], [80.0, 180.0], [85.0, -90.0]]);
+        let a2 = arr2(&[[81.0, 10.0], [82.0, 100.0], [79.0, -100.0]]);
+        let metric = Haversine::new();
+        let nearest = a1
+            .rows()
+            .into_iter()
+            .map(|x| {
+                a2.rows()
+                    .into_iter()
+                    .map(|y| Metric::<f64>::distance(&metric, metric.reduced_distance(x, y)))
+                    .fold(f64::INFINITY, f64::min)
+            })
+            .collect::<Vec<_>>();
+        for workers in [1, 3] {
+            let options = HausdorffOptions::new().workers(workers).metric(metric);
+            let result = crate::try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+            assert_eq!(result.distance, nearest[result.ar1_index]);
+            assert!(nearest.iter().all(|&d| d <= result.distance));
+            // the same in single precision
+            let (b1, b2) = (a1.mapv(|x| x as f32), a2.mapv(|x| x as f32));
+            let single = crate::try_directed_hausdorff_with(&b1, &b2, &options).unwrap();
+            assert_eq!(single.ar1_index, result.ar1_index);
+            assert!((single.distance as f64 - result.distance).abs() < 10.0);
+        }
+    }
+
+    #[test]
+    fn invalid_metrics() {
+        assert!(std::panic::catch_unwind(|| Minkowski::new(0.5)).is_err());
+        assert!(std::panic::catch_unwind(|| Minkowski::new(f64::INFINITY)).is_err());
+        assert!(std::panic::catch_unwind(|| Haversine::new().radius(0.0)).is_err());
+        assert!(std::panic::catch_unwind(|| WeightedEuclidean::new([1.0, -1.0])).is_err());
+        let metric = WeightedEuclidean::new([1.0, 2.0]);
+        assert_eq!(Metric::<f64>::check_columns(&metric, 2), Ok(()));
+        assert_eq!(
+            Metric::<f64>::check_columns(&metric, 3),
+            Err(HausdorffError::InvalidMetric(
+                "2 weights for points with 3 columns".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,457 @@
+//! Nearest neighbour searches, reporting the nearest neighbour of
+//! every point or of every point beyond a tolerance, and behind the
+//! distances that depend on every nearest neighbour distance rather
+//! than only the largest.
+
+use crate::coordinate::{Accumulator, Coordinate, Distance};
+use crate::decision::Threshold;
+use crate::error::HausdorffError;
+use crate::input::{apply_non_finite_policy, check_shapes, first_non_finite};
+use crate::metric::Metric;
+use crate::options::{HausdorffOptions, NonFinitePolicy};
+use crate::parallel::{estimated_work, Batches, Executor};
+use crate::result::Outliers;
+use crate::shuffle::RowOrders;
+use ndarray::{Array1, ArrayView2, AsArray, Ix2};
+use num_traits::{Float, ToPrimitive};
+
+/// Find the nearest neighbour in `ar2` of every row of `ar1`,
+/// configured by [`HausdorffOptions`].
+///
+/// Returns the distance from each row of `ar1` to its nearest
+/// neighbour, and the index of that neighbour in `ar2`, for instance
+/// to colour a surface by its error. The directed Hausdorff distance
+/// is the largest of these distances.
+///
+/// Every point needs its exact nearest neighbour, so there are no
+/// early breaks, but the rows are distributed over the workers in
+/// batches in the same way. Of several neighbours at the same distance,
+/// the first in the (shuffled) order of the rows of `ar2` is reported.
+///
+/// A row with no nearest neighbour reports a NaN distance and
+/// neighbour `usize::MAX`. Under [`NonFinitePolicy::Skip`], these are
+/// the rows of `ar1` with non-finite coordinates, and the rows of `ar2`
+/// with non-finite coordinates are never neighbours. Under
+/// [`NonFinitePolicy::Propagate`], these are the same rows of `ar1`,
+/// or every row if `ar2` has non-finite coordinates.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0, 1.0], [3.0, 1.0], [5.0, 4.0]]);
+/// let a2 = arr2(&[[0.0, 0.0], [4.0, 1.0]]);
+/// let options = HausdorffOptions::new();
+/// let (distances, neighbours) = try_nearest_neighbours_with(&a1, &a2, &options).unwrap();
+/// assert_eq!(distances, arr1(&[1.0, 1.0, 10.0_f64.sqrt()]));
+/// assert_eq!(neighbours, arr1(&[0, 1, 1]));
+/// ```
+pub fn try_nearest_neighbours_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    options: &HausdorffOptions<M>,
+) -> Result<(Array1<Distance<T>>, Array1<usize>), HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    let ar1 = ar1.into();
+    let orders = &mut RowOrders::default();
+    let nearest = nearest_with(ar1, ar2.into(), None, options, backend, orders)?;
+    Ok(scatter_rows(ar1.nrows(), nearest))
+}
+
+/// Find the rows of `ar1` that are farther than `tolerance` from
+/// every row of `ar2`, configured by [`HausdorffOptions`].
+///
+/// Reports the [`Outliers`], with the distances and nearest
+/// neighbours of [`try_nearest_neighbours_with`]. Rows that report a NaN distance
+/// because of [`NonFinitePolicy::Propagate`] are always included, with
+/// neighbour `usize::MAX`.
+///
+/// As in [`try_directed_hausdorff_within_with`](crate::try_directed_hausdorff_within_with),
+/// the search of a row stops at the first point of `ar2` within
+/// `tolerance`, so only the outliers are searched exhaustively.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0, 1.0], [3.0, 1.0], [5.0, 4.0]]);
+/// let a2 = arr2(&[[0.0, 0.0], [4.0, 1.0]]);
+/// let options = HausdorffOptions::new();
+/// let outliers = try_nearest_neighbours_beyond_with(&a1, &a2, 1.0, &options).unwrap();
+/// assert_eq!(outliers.rows, arr1(&[2]));
+/// assert_eq!(outliers.distances, arr1(&[10.0_f64.sqrt()]));
+/// assert_eq!(outliers.neighbours, arr1(&[1]));
+/// ```
+pub fn try_nearest_neighbours_beyond_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    tolerance: Distance<T>,
+    options: &HausdorffOptions<M>,
+) -> Result<Outliers<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    let nearest = nearest_with(
+        ar1.into(),
+        ar2.into(),
+        Some(tolerance),
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )?;
+    Ok(outliers(nearest))
+}
+
+/// The calculation behind the free functions and the corresponding
+/// [`HausdorffEngine`](crate::HausdorffEngine) methods. Returns
+/// `(i, distance, j)` for each row `i` of `ar1` that is not skipped,
+/// or, given a `tolerance`, only for those farther than it, in the
+/// order of the rows.
+pub(crate) fn nearest_with<T: Coordinate, M: Metric<T>>(
+    ar1: ArrayView2<T>,
+    ar2: ArrayView2<T>,
+    tolerance: Option<Distance<T>>,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+    orders: &mut RowOrders,
+) -> Result<Vec<(usize, Distance<T>, usize)>, HausdorffError> {
+    check_shapes(&ar1, &ar2)?;
+    let metric = &options.metric;
+    metric.check_columns(ar1.ncols())?;
+    orders.prepare_forward(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    let RowOrders {
+        rows1, inner_rows2, ..
+    } = orders;
+    let nan = Distance::<T>::nan();
+    let mut propagated = Vec::new();
+    if options.non_finite_policy == NonFinitePolicy::Propagate {
+        if first_non_finite(&ar2).is_some() {
+            return Ok((0..ar1.nrows()).map(|i| (i, nan, usize::MAX)).collect());
+        }
+        rows1.retain(|&i| {
+            let finite = ar1.row(i).iter().all(|x| x.is_finite());
+            if !finite {
+                propagated.push((i, nan, usize::MAX));
+            }
+            finite
+        });
+    } else {
+        let policy = options.non_finite_policy;
+        apply_non_finite_policy(&ar1, &ar2, metric, policy, rows1, inner_rows2)?;
+    }
+
+    let work = estimated_work(rows1.len(), inner_rows2.len(), ar1.ncols());
+    let available = executor.available_workers();
+    let workers = options.workers.resolve(rows1.len(), work, available);
+    let nearest = match tolerance {
+        Some(tolerance) => {
+            let threshold = Threshold::new(metric, tolerance);
+            beyond_batches(
+                &ar1,
+                &ar2,
+                metric,
+                rows1,
+                inner_rows2,
+                &threshold,
+                workers,
+                executor,
+            )?
+        }
+        None => {
+            nearest_neighbour_batches(&ar1, &ar2, metric, rows1, inner_rows2, workers, executor)?
+        }
+    };
+    let mut nearest: Vec<_> = nearest
+        .into_iter()
+        .map(|(i, d, j)| (i, metric.distance(d), j))
+        .chain(propagated)
+        .collect();
+    nearest.sort_unstable_by_key(|&(i, _, _)| i);
+    Ok(nearest)
+}
+
+/// Spread the `nearest` neighbours over arrays indexed by the
+/// rows of `ar1`, leaving NaN and `usize::MAX` for the skipped rows.
+pub(crate) fn scatter_rows<D: Float>(
+    rows: usize,
+    nearest: Vec<(usize, D, usize)>,
+) -> (Array1<D>, Array1<usize>) {
+    let mut distances = Array1::from_elem(rows, D::nan());
+    let mut neighbours = Array1::from_elem(rows, usize::MAX);
+    for (i, d, j) in nearest {
+        distances[i] = d;
+        neighbours[i] = j;
+    }
+    (distances, neighbours)
+}
+
+/// Collect the `nearest` neighbours of the rows beyond a tolerance.
+pub(crate) fn outliers<T: Coordinate>(nearest: Vec<(usize, Distance<T>, usize)>) -> Outliers<T> {
+    let (mut rows, mut distances, mut neighbours) = (Vec::new(), Vec::new(), Vec::new());
+    for (i, d, j) in nearest {
+        rows.push(i);
+        distances.push(d);
+        neighbours.push(j);
+    }
+    Outliers {
+        rows: rows.into(),
+        distances: distances.into(),
+        neighbours: neighbours.into(),
+    }
+}
+
+/// The nearest neighbour of row `i` of `ar1` among the `inner_rows`
+/// of `ar2`, unless some row is closer than `bound`.
+pub(crate) fn nearest_neighbour<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+    i: usize,
+    inner_rows: &[usize],
+    bound: T::Accumulator,
+) -> Option<(T::Accumulator, usize)> {
+    let point = ar1.row(i);
+    let mut cmin = T::Accumulator::MAX;
+    let mut j_store = 0;
+    for &j in inner_rows {
+        let d = metric.reduced_distance(point, ar2.row(j));
+        if d < bound {
+            return None;
+        }
+        if d < cmin {
+            cmin = d;
+            j_store = j;
+        }
+    }
+    Some((cmin, j_store))
+}
+
+/// The nearest neighbour, among the `inner_rows` of `ar2`, of each
+/// of the `rows` of `ar1`, searched by `workers` workers. Returns
+/// `(i, reduced distance, j)` for each row `i`, in the order of the
+/// rows of `ar1`, so that sums over them do not depend on the
+/// visiting order or on the division of the work.
+pub(crate) fn nearest_neighbour_batches<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+    rows: &[usize],
+    inner_rows: &[usize],
+    workers: usize,
+    executor: Executor,
+) -> Result<Vec<(usize, T::Accumulator, usize)>, HausdorffError> {
+    let batches = Batches::new(rows.len(), workers);
+    let search = |_| {
+        let mut nearest = Vec::new();
+        while let Some(batch) = batches.next_batch() {
+            for &i in &rows[batch] {
+                let zero = T::Accumulator::ZERO;
+                let (d, j) = nearest_neighbour(ar1, ar2, metric, i, inner_rows, zero)
+                    .expect("no bound to break on");
+                nearest.push((i, d, j));
+            }
+        }
+        nearest
+    };
+    let mut nearest = if workers <= 1 {
+        // single thread/serial approach
+        search(0)
+    } else {
+        executor.run_workers(workers, search)?.concat()
+    };
+    nearest.sort_unstable_by_key(|&(i, _, _)| i);
+    Ok(nearest)
+}
+
+/// The nearest neighbour, among the `inner_rows` of `ar2`, of each of
+/// the `rows` of `ar1` with no point of `ar2` within the `threshold`,
+/// searched by `workers` workers, in the order of the rows of `ar1`.
+/// The search of every other row stops at its first point within.
+#[allow(clippy::too_many_arguments)]
+fn beyond_batches<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+    rows: &[usize],
+    inner_rows: &[usize],
+    threshold: &Threshold<T, M>,
+    workers: usize,
+    executor: Executor,
+) -> Result<Vec<(usize, T::Accumulator, usize)>, HausdorffError> {
+    let batches = Batches::new(rows.len(), workers);
+    let search = |_| {
+        let mut threshold = threshold.clone();
+        let mut beyond = Vec::new();
+        while let Some(batch) = batches.next_batch() {
+            'rows: for &i in &rows[batch] {
+                let point = ar1.row(i);
+                let mut cmin = T::Accumulator::MAX;
+                let mut j_store = 0;
+                for &j in inner_rows {
+                    let d = metric.reduced_distance(point, ar2.row(j));
+                    if threshold.contains(d) {
+                        continue 'rows;
+                    }
+                    if d < cmin {
+                        cmin = d;
+                        j_store = j;
+                    }
+                }
+                beyond.push((i, cmin, j_store));
+            }
+        }
+        beyond
+    };
+    let mut beyond = if workers <= 1 {
+        // single thread/serial approach
+        search(0)
+    } else {
+        executor.run_workers(workers, search)?.concat()
+    };
+    beyond.sort_unstable_by_key(|&(i, _, _)| i);
+    Ok(beyond)
+}
+
+/// The distances from each of the `rows` of `ar1` to its nearest
+/// neighbour among the `inner_rows` of `ar2`, in row order,
+/// searched by as many workers as the `options` allow.
+pub(crate) fn nearest_distances<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    rows: &[usize],
+    inner_rows: &[usize],
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+) -> Result<Vec<f64>, HausdorffError> {
+    let metric = &options.metric;
+    let work = estimated_work(rows.len(), inner_rows.len(), ar1.ncols());
+    let available = executor.available_workers();
+    let workers = options.workers.resolve(rows.len(), work, available);
+    let nearest = nearest_neighbour_batches(ar1, ar2, metric, rows, inner_rows, workers, executor)?;
+    // in f64 whatever the distance type, for accurate sums
+    Ok(nearest
+        .into_iter()
+        .map(|(_, d, _)| {
+            metric
+                .distance(d)
+                .to_f64()
+                .expect("a floating point distance")
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures, try_directed_hausdorff_with, Shuffle};
+    use ndarray::prelude::*;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(21);
+        let a1 = Array2::from_shape_fn((300, 3), |_| rng.gen_range(-1.0..1.0));
+        let a2 = Array2::from_shape_fn((80, 3), |_| rng.gen_range(-1.0..1.0));
+        let mut expected = (Array1::zeros(300), Array1::zeros(300));
+        for (i, x) in a1.rows().into_iter().enumerate() {
+            for (j, y) in a2.rows().into_iter().enumerate() {
+                let d = f64::sqrt((&x - &y).mapv(|c| c * c).sum());
+                if j == 0 || d < expected.0[i] {
+                    expected.0[i] = d;
+                    expected.1[i] = j;
+                }
+            }
+        }
+        let directed = try_directed_hausdorff_with(&a1, &a2, &HausdorffOptions::new()).unwrap();
+        assert_eq!(directed.distance, expected.0.fold(0.0, |a, &b| b.max(a)));
+        let tolerance = 0.3;
+        let rows: Vec<_> = (0..300).filter(|&i| expected.0[i] > tolerance).collect();
+        let rows = Array1::from(rows);
+        let outliers = Outliers {
+            distances: rows.mapv(|i| expected.0[i]),
+            neighbours: rows.mapv(|i| expected.1[i]),
+            rows,
+        };
+        for workers in [1, 0, 2, 5] {
+            for shuffle in [Shuffle::default(), Shuffle::SciPy(3), Shuffle::Disabled] {
+                let options = HausdorffOptions::new().workers(workers).shuffle(shuffle);
+                let actual = try_nearest_neighbours_with(&a1, &a2, &options).unwrap();
+                assert_eq!(actual, expected);
+                let actual = try_nearest_neighbours_beyond_with(&a1, &a2, tolerance, &options);
+                assert_eq!(actual.unwrap(), outliers);
+            }
+        }
+    }
+
+    #[test]
+    fn scipy_reference_values() {
+        // SciPy's farthest point of a2 and its neighbour in a1,
+        // where the next farthest, [10, 9], is √53 from [3, 7]
+        let (a1, a2) = fixtures::indices();
+        let (distance, row, neighbour) = fixtures::INDICES_REVERSE;
+        let options = HausdorffOptions::new();
+        let (distances, neighbours) = try_nearest_neighbours_with(&a2, &a1, &options).unwrap();
+        assert_eq!(distances, arr1(&[0.0, 0.0, distance, 53_f64.sqrt()]));
+        assert_eq!(neighbours, arr1(&[1, 2, neighbour, 3]));
+        let outliers = try_nearest_neighbours_beyond_with(&a2, &a1, 93.0, &options).unwrap();
+        assert_eq!(outliers.rows, arr1(&[row]));
+        assert_eq!(outliers.neighbours, arr1(&[neighbour]));
+        // and the largest distances of the paths of test_symmetry
+        let (path_1, path_2) = fixtures::paths();
+        for (a1, a2, expected) in [
+            (&path_1, &path_2, fixtures::PATHS_FORWARD),
+            (&path_2, &path_1, fixtures::PATHS_REVERSE),
+        ] {
+            let (distances, _) = try_nearest_neighbours_with(a1, a2, &options).unwrap();
+            assert_eq!(distances.fold(0.0, |a: f64, &b| a.max(b)), expected);
+            let outliers = try_nearest_neighbours_beyond_with(a1, a2, expected, &options);
+            assert!(outliers.unwrap().rows.is_empty());
+        }
+    }
+
+    #[test]
+    fn non_finite_policies() {
+        let a1 = arr2(&[[0.0_f32, 0.0], [f32::NAN, 1.0], [0.0, 10.0]]);
+        let a2 = arr2(&[[0.0, 1.0], [0.0, 4.0]]);
+        let options = HausdorffOptions::new();
+        assert!(try_nearest_neighbours_with(&a1, &a2, &options).is_err());
+        for policy in [NonFinitePolicy::Skip, NonFinitePolicy::Propagate] {
+            let options = options.clone().non_finite_policy(policy);
+            let (distances, neighbours) = try_nearest_neighbours_with(&a1, &a2, &options).unwrap();
+            assert_eq!(distances[[0]], 1.0);
+            assert!(distances[[1]].is_nan());
+            assert_eq!(distances[[2]], 6.0);
+            assert_eq!(neighbours, arr1(&[0, usize::MAX, 1]));
+            let outliers = try_nearest_neighbours_beyond_with(&a1, &a2, 1.0, &options).unwrap();
+            // the skipped rows are not outliers
+            match policy {
+                NonFinitePolicy::Skip => assert_eq!(outliers.rows, arr1(&[2])),
+                _ => {
+                    assert_eq!(outliers.rows, arr1(&[1, 2]));
+                    assert_eq!(outliers.neighbours, arr1(&[usize::MAX, 1]));
+                }
+            }
+        }
+        // a non-finite neighbour poisons every distance
+        let options = options.non_finite_policy(NonFinitePolicy::Propagate);
+        let (distances, neighbours) = try_nearest_neighbours_with(&a2, &a1, &options).unwrap();
+        assert!(distances.iter().all(|d| d.is_nan()));
+        assert_eq!(neighbours, arr1(&[usize::MAX, usize::MAX]));
+        let options = options.non_finite_policy(NonFinitePolicy::Skip);
+        let (distances, neighbours) = try_nearest_neighbours_with(&a2, &a1, &options).unwrap();
+        assert_eq!(distances, arr1(&[1.0, 4.0]));
+        assert_eq!(neighbours, arr1(&[0, 0]));
+    }
+}
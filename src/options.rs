@@ -0,0 +1,276 @@
+//! Configuration of the Hausdorff distance calculations.
+
+use crate::metric::Euclidean;
+use crate::parallel::Backend;
+use crate::shuffle::Shuffle;
+
+/// How coordinates that are NaN or infinite are handled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Report [`HausdorffError::NonFinite`](crate::HausdorffError::NonFinite)
+    /// for the first non-finite coordinate found.
+    #[default]
+    Reject,
+    /// Ignore any row, in either array, that contains a non-finite
+    /// coordinate, as if the point were missing. If no rows remain
+    /// in an array, [`HausdorffError::EmptyInput`](crate::HausdorffError::EmptyInput)
+    /// is reported.
+    Skip,
+    /// Return a NaN distance if any coordinate is non-finite, the
+    /// way a NaN poisons a floating point maximum. The witness
+    /// indices then point at the first offending row (and at row 0
+    /// of the other array).
+    Propagate,
+}
+
+/// How many worker threads a calculation is distributed over.
+///
+/// A `usize` converts to [`Workers::Fixed`], so that
+/// [`HausdorffOptions::workers`] accepts a plain count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Workers {
+    /// This many workers, or one per row if there are fewer rows, with
+    /// `1` calculating serially on the calling thread and `0` using
+    /// every available thread.
+    Fixed(usize),
+    /// As many workers as the size of each calculation warrants,
+    /// falling back to a serial calculation for small inputs.
+    Auto(AutoWorkers),
+}
+
+impl Default for Workers {
+    fn default() -> Self {
+        Workers::Fixed(0)
+    }
+}
+
+impl From<usize> for Workers {
+    fn from(workers: usize) -> Self {
+        Workers::Fixed(workers)
+    }
+}
+
+impl From<AutoWorkers> for Workers {
+    fn from(auto: AutoWorkers) -> Self {
+        Workers::Auto(auto)
+    }
+}
+
+impl Workers {
+    /// [`Workers::Auto`] with the default thresholds.
+    pub fn auto() -> Self {
+        Workers::Auto(AutoWorkers::default())
+    }
+
+    /// The number of workers for a calculation of the estimated `work`
+    /// over `rows` rows, when `available` can run at once. There are
+    /// never more workers than rows, since any more would have nothing
+    /// to do but start up.
+    pub(crate) fn resolve(self, rows: usize, work: u64, available: usize) -> usize {
+        let workers = match self {
+            Workers::Fixed(0) => available,
+            Workers::Fixed(workers) => workers,
+            Workers::Auto(auto) => auto.workers(work, available),
+        };
+        workers.min(rows).max(1)
+    }
+}
+
+/// The thresholds by which [`Workers::Auto`] picks the number of
+/// workers, from the work of a calculation estimated as
+/// `nrows(ar1) * nrows(ar2) * ncols`, i.e., the number of coordinate
+/// differences needed without any early breaks (twice that for the
+/// symmetric distance).
+///
+/// By default, both thresholds are `2^18`, a conservative starting
+/// point rather than a measurement. `cargo bench --bench auto_workers`
+/// times the break even points on the machine it runs on, and suggests
+/// thresholds calibrated for it.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// let auto = AutoWorkers::new().serial_below(100_000).max_workers(4);
+/// let options = HausdorffOptions::new().workers(auto);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoWorkers {
+    serial_below: u64,
+    work_per_worker: u64,
+    max_workers: usize,
+}
+
+impl Default for AutoWorkers {
+    fn default() -> Self {
+        AutoWorkers {
+            serial_below: 1 << 18,
+            work_per_worker: 1 << 18,
+            max_workers: 0,
+        }
+    }
+}
+
+impl AutoWorkers {
+    /// The default thresholds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calculate serially on the calling thread
+    /// when the estimated work is below `work`.
+    pub fn serial_below(mut self, work: u64) -> Self {
+        self.serial_below = work;
+        self
+    }
+
+    /// The estimated work that warrants each additional worker,
+    /// since every worker adds a fixed start up cost.
+    pub fn work_per_worker(mut self, work: u64) -> Self {
+        self.work_per_worker = work;
+        self
+    }
+
+    /// The most workers to use, with `0` (the
+    /// default) meaning every available thread.
+    pub fn max_workers(mut self, workers: usize) -> Self {
+        self.max_workers = workers;
+        self
+    }
+
+    fn workers(self, work: u64, available: usize) -> usize {
+        if work < self.serial_below {
+            return 1;
+        }
+        let max = match self.max_workers {
+            0 => available,
+            max => max,
+        };
+        let wanted = work / self.work_per_worker.max(1);
+        usize::try_from(wanted)
+            .unwrap_or(usize::MAX)
+            .clamp(1, max.max(1))
+    }
+}
+
+/// Options for the Hausdorff distance calculations, constructed
+/// with [`HausdorffOptions::new`] and adjusted with the builder
+/// style methods below.
+///
+/// The options are generic over the [`Metric`](crate::Metric)
+/// `M`, which is [`Euclidean`] unless another is set with
+/// [`HausdorffOptions::metric`].
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// let options = HausdorffOptions::new()
+///     .workers(4)
+///     .non_finite_policy(NonFinitePolicy::Skip)
+///     .shuffle(Shuffle::SciPy(0))
+///     .metric(Manhattan);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HausdorffOptions<M = Euclidean> {
+    pub(crate) workers: Workers,
+    pub(crate) non_finite_policy: NonFinitePolicy,
+    pub(crate) shuffle: Shuffle,
+    pub(crate) symmetric_pruning: bool,
+    pub(crate) shared_bound: bool,
+    pub(crate) backend: Backend,
+    pub(crate) metric: M,
+}
+
+impl HausdorffOptions {
+    /// Calculation on all available threads, rejecting non-finite
+    /// coordinates and using the default [`Shuffle`] and the
+    /// [`Euclidean`] metric.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<M> HausdorffOptions<M> {
+    /// The number of threads to distribute the calculation over,
+    /// with `1` calculating serially on the calling thread and `0`
+    /// (the default) using the available parallelism of the machine,
+    /// or [`Workers::Auto`] to decide for each calculation.
+    pub fn workers(mut self, workers: impl Into<Workers>) -> Self {
+        self.workers = workers.into();
+        self
+    }
+
+    /// How NaN or infinite coordinates are handled.
+    pub fn non_finite_policy(mut self, policy: NonFinitePolicy) -> Self {
+        self.non_finite_policy = policy;
+        self
+    }
+
+    /// The order in which the rows of both arrays are visited.
+    pub fn shuffle(mut self, shuffle: Shuffle) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Whether the symmetric calculation may prune the reverse
+    /// direction using the running maximum of the forward direction,
+    /// in which case the reverse witness is only reported if it
+    /// realises the symmetric distance. Disabled by default.
+    pub fn symmetric_pruning(mut self, prune: bool) -> Self {
+        self.symmetric_pruning = prune;
+        self
+    }
+
+    /// Whether the worker threads publish their running maximum to
+    /// each other through an atomic, so that every thread breaks
+    /// early against the largest distance found so far by any of
+    /// them. The results are identical either way, but sharing
+    /// usually pays off for large arrays. Disabled by default.
+    pub fn shared_bound(mut self, share: bool) -> Self {
+        self.shared_bound = share;
+        self
+    }
+
+    /// Where the workers run, by default on threads spawned for
+    /// each call. With the `rayon` feature, `0` workers then means
+    /// the number of threads in the rayon pool.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// The [`Metric`](crate::Metric) by which the
+    /// distances between points are measured.
+    pub fn metric<N>(self, metric: N) -> HausdorffOptions<N> {
+        HausdorffOptions {
+            workers: self.workers,
+            non_finite_policy: self.non_finite_policy,
+            shuffle: self.shuffle,
+            symmetric_pruning: self.symmetric_pruning,
+            shared_bound: self.shared_bound,
+            backend: self.backend,
+            metric,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_workers() {
+        let auto = AutoWorkers::new().serial_below(1000).work_per_worker(500);
+        assert_eq!(auto.workers(999, 8), 1);
+        assert_eq!(auto.workers(1000, 8), 2);
+        assert_eq!(auto.workers(2600, 8), 5);
+        assert_eq!(auto.workers(u64::MAX, 8), 8);
+        assert_eq!(auto.max_workers(3).workers(u64::MAX, 8), 3);
+        assert_eq!(auto.work_per_worker(0).workers(1000, 8), 8);
+        let options = HausdorffOptions::new().workers(auto);
+        assert_eq!(options.workers.resolve(100, 2600, 8), 5);
+        assert_eq!(options.workers.resolve(3, 2600, 8), 3);
+        assert_eq!(HausdorffOptions::new().workers, Workers::Fixed(0));
+    }
+}
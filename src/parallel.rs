@@ -0,0 +1,416 @@
+//! Distribution of the rows of the first array over worker threads.
+
+use crate::error::HausdorffError;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// Where the workers of a parallel calculation run.
+///
+/// With the `rayon` feature enabled, the workers may run as tasks on
+/// a rayon thread pool the application already has, rather than on
+/// threads spawned for each call, which would oversubscribe the
+/// machine alongside the pool.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub enum Backend {
+    /// Scoped standard library threads, spawned for each call.
+    #[default]
+    Threads,
+    /// The rayon pool of the calling thread when called from within
+    /// one, and the global rayon pool otherwise.
+    #[cfg(feature = "rayon")]
+    Rayon,
+    /// A caller provided rayon pool.
+    #[cfg(feature = "rayon")]
+    RayonPool(std::sync::Arc<rayon::ThreadPool>),
+}
+
+impl Backend {
+    /// The number of workers that can run at once: the available
+    /// parallelism of the machine, or the threads of the rayon pool.
+    pub(crate) fn available_workers(&self) -> usize {
+        match self {
+            Backend::Threads => thread::available_parallelism().map_or(1, |n| n.get()),
+            #[cfg(feature = "rayon")]
+            Backend::Rayon => rayon::current_num_threads(),
+            #[cfg(feature = "rayon")]
+            Backend::RayonPool(pool) => pool.current_num_threads(),
+        }
+    }
+
+    /// Run `task` for each worker index, collecting
+    /// the results in worker order.
+    pub(crate) fn run_workers<R, F>(
+        &self,
+        workers: usize,
+        task: F,
+    ) -> Result<Vec<R>, HausdorffError>
+    where
+        R: Send,
+        F: Fn(usize) -> R + Sync,
+    {
+        match self {
+            Backend::Threads => run_workers(workers, task),
+            #[cfg(feature = "rayon")]
+            Backend::Rayon => run_rayon_tasks(workers, task),
+            #[cfg(feature = "rayon")]
+            Backend::RayonPool(pool) => {
+                let task = &task;
+                pool.install(move || run_rayon_tasks(workers, task))
+            }
+        }
+    }
+}
+
+/// The work of searching `rows` rows for their nearest neighbours
+/// among `inner_rows` rows with `columns` columns, without any early
+/// breaks, as estimated for [`Workers::Auto`](crate::Workers::Auto).
+pub(crate) fn estimated_work(rows: usize, inner_rows: usize, columns: usize) -> u64 {
+    (rows as u64)
+        .saturating_mul(inner_rows as u64)
+        .saturating_mul(columns as u64)
+}
+
+/// Consecutive batches of rows, handed out to whichever worker asks
+/// next, so that workers whose rows break early quickly take on more
+/// of the rows rather than waiting for the slowest worker.
+pub(crate) struct Batches {
+    next: AtomicUsize,
+    len: usize,
+    size: usize,
+}
+
+impl Batches {
+    /// Split `len` rows into batches small enough that each
+    /// of the `workers` can expect to take several of them.
+    pub(crate) fn new(len: usize, workers: usize) -> Self {
+        Batches {
+            next: AtomicUsize::new(0),
+            len,
+            size: (len / (workers * 16)).max(1),
+        }
+    }
+
+    /// The next batch of rows, or `None` once all rows
+    /// have been handed out.
+    pub(crate) fn next_batch(&self) -> Option<Range<usize>> {
+        let start = self.next.fetch_add(self.size, Ordering::Relaxed);
+        (start < self.len).then(|| start..(start + self.size).min(self.len))
+    }
+}
+
+/// Run `task` for each worker index on its own scoped thread,
+/// collecting the results in worker order.
+pub(crate) fn run_workers<R, F>(workers: usize, task: F) -> Result<Vec<R>, HausdorffError>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync,
+{
+    let task = &task;
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers).map(|k| scope.spawn(move || task(k))).collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().map_err(HausdorffError::from_panic))
+            .collect()
+    })
+}
+
+/// What runs the workers of a calculation: either the [`Backend`]
+/// of the options, or the long lived pool of a
+/// [`HausdorffEngine`](crate::HausdorffEngine).
+#[derive(Clone, Copy)]
+pub(crate) enum Executor<'a> {
+    Backend(&'a Backend),
+    Pool(&'a WorkerPool),
+}
+
+impl Executor<'_> {
+    /// The number of workers that can run at once.
+    pub(crate) fn available_workers(self) -> usize {
+        match self {
+            Executor::Backend(backend) => backend.available_workers(),
+            Executor::Pool(pool) => pool.threads.len(),
+        }
+    }
+
+    /// Run `task` for each worker index, collecting
+    /// the results in worker order.
+    pub(crate) fn run_workers<R, F>(self, workers: usize, task: F) -> Result<Vec<R>, HausdorffError>
+    where
+        R: Send,
+        F: Fn(usize) -> R + Sync,
+    {
+        match self {
+            Executor::Backend(backend) => backend.run_workers(workers, task),
+            Executor::Pool(pool) => pool.run_workers(workers, task),
+        }
+    }
+}
+
+/// A task of the pool, borrowing the data of the calling thread.
+type Job<'a> = dyn Fn(usize) + Sync + 'a;
+
+/// Long lived worker threads, which run the tasks of one
+/// calculation at a time, so that repeated calculations
+/// do not pay for spawning threads.
+pub(crate) struct WorkerPool {
+    shared: Arc<PoolShared>,
+    threads: Vec<thread::JoinHandle<()>>,
+    // only one calculation may hand out tasks at a time
+    submit: Mutex<()>,
+}
+
+struct PoolShared {
+    state: Mutex<PoolState>,
+    work: Condvar,
+    done: Condvar,
+}
+
+#[derive(Default)]
+struct PoolState {
+    // the lifetime of the job is erased; it is only valid
+    // while some of its tasks have not yet finished
+    job: Option<&'static Job<'static>>,
+    next: usize,
+    tasks: usize,
+    remaining: usize,
+    shutdown: bool,
+}
+
+impl PoolShared {
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        // tasks never panic while holding the lock, but
+        // a poisoned lock would still be consistent
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn work_loop(&self) {
+        let mut state = self.lock();
+        loop {
+            if state.shutdown {
+                return;
+            }
+            match state.job {
+                Some(job) if state.next < state.tasks => {
+                    let k = state.next;
+                    state.next += 1;
+                    drop(state);
+                    job(k);
+                    state = self.lock();
+                    state.remaining -= 1;
+                    if state.remaining == 0 {
+                        self.done.notify_all();
+                    }
+                }
+                _ => {
+                    state = self
+                        .work
+                        .wait(state)
+                        .unwrap_or_else(PoisonError::into_inner)
+                }
+            }
+        }
+    }
+}
+
+impl WorkerPool {
+    pub(crate) fn new(threads: usize) -> Self {
+        let shared = Arc::new(PoolShared {
+            state: Mutex::new(PoolState::default()),
+            work: Condvar::new(),
+            done: Condvar::new(),
+        });
+        let threads = (0..threads)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || shared.work_loop())
+            })
+            .collect();
+        WorkerPool {
+            shared,
+            threads,
+            submit: Mutex::new(()),
+        }
+    }
+
+    /// Run `task` for each worker index on the threads of the
+    /// pool, collecting the results in worker order.
+    fn run_workers<R, F>(&self, workers: usize, task: F) -> Result<Vec<R>, HausdorffError>
+    where
+        R: Send,
+        F: Fn(usize) -> R + Sync,
+    {
+        let _submit = self.submit.lock().unwrap_or_else(PoisonError::into_inner);
+        let slots: Vec<Mutex<Option<thread::Result<R>>>> =
+            (0..workers).map(|_| Mutex::new(None)).collect();
+        let job = |k: usize| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| task(k)));
+            *slots[k].lock().unwrap_or_else(PoisonError::into_inner) = Some(result);
+        };
+        let job: &Job<'_> = &job;
+        // SAFETY: the threads only call the job after it is published
+        // below and before its last task finishes, and nothing between
+        // publishing it and waiting for the last task can unwind, so
+        // the job outlives every call despite the erased lifetime.
+        let job = unsafe { std::mem::transmute::<&Job<'_>, &'static Job<'static>>(job) };
+        let mut state = self.shared.lock();
+        *state = PoolState {
+            job: Some(job),
+            next: 0,
+            tasks: workers,
+            remaining: workers,
+            shutdown: false,
+        };
+        self.shared.work.notify_all();
+        while state.remaining > 0 {
+            state = self
+                .shared
+                .done
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        state.job = None;
+        drop(state);
+        slots
+            .into_iter()
+            .map(|slot| {
+                let result = slot.into_inner().unwrap_or_else(PoisonError::into_inner);
+                result
+                    .expect("every task has run")
+                    .map_err(HausdorffError::from_panic)
+            })
+            .collect()
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.work.notify_all();
+        for thread in self.threads.drain(..) {
+            // the tasks catch their own panics
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Run `task` for each worker index as a task on the current
+/// rayon pool, collecting the results in worker order.
+#[cfg(feature = "rayon")]
+fn run_rayon_tasks<R, F>(workers: usize, task: F) -> Result<Vec<R>, HausdorffError>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync,
+{
+    use rayon::prelude::*;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    // rayon would resume a panic on the calling thread,
+    // so catch it to report it like a panicked thread
+    (0..workers)
+        .into_par_iter()
+        .map(|k| catch_unwind(AssertUnwindSafe(|| task(k))).map_err(HausdorffError::from_panic))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Workers;
+
+    #[test]
+    fn batches_cover_all_rows() {
+        let batches = Batches::new(100, 2);
+        let all: Vec<_> = std::iter::from_fn(|| batches.next_batch()).collect();
+        assert_eq!(all.len(), 34);
+        assert_eq!(all[0], 0..3);
+        assert_eq!(all[33], 99..100);
+        assert!(all.windows(2).all(|pair| pair[0].end == pair[1].start));
+        assert_eq!(batches.next_batch(), None);
+        let batches = Batches::new(2, 4);
+        assert_eq!(batches.next_batch(), Some(0..1));
+        assert_eq!(batches.next_batch(), Some(1..2));
+        assert_eq!(batches.next_batch(), None);
+        assert_eq!(Batches::new(0, 4).next_batch(), None);
+    }
+
+    #[test]
+    fn zero_workers_uses_available_parallelism() {
+        assert!(Backend::Threads.available_workers() >= 1);
+        assert_eq!(Workers::Fixed(0).resolve(10, 1, 6), 6);
+        assert_eq!(Workers::Fixed(3).resolve(10, 1, 6), 3);
+        // but never more workers than rows
+        assert_eq!(Workers::Fixed(16).resolve(1, 1, 6), 1);
+        assert_eq!(Workers::Fixed(0).resolve(4, 1, 6), 4);
+    }
+
+    #[test]
+    fn worker_panics_are_reported() {
+        let result = run_workers(3, |k| {
+            if k == 1 {
+                panic!("worker {} failed", k);
+            }
+            k
+        });
+        assert_eq!(
+            result,
+            Err(HausdorffError::WorkerFailed("worker 1 failed".to_string()))
+        );
+        assert_eq!(run_workers(3, |k| k * 2), Ok(vec![0, 2, 4]));
+    }
+
+    #[test]
+    fn pool_is_reused() {
+        let pool = WorkerPool::new(3);
+        let executor = Executor::Pool(&pool);
+        assert_eq!(executor.available_workers(), 3);
+        for round in 0..100 {
+            let offset = round;
+            let expected: Vec<_> = (0..7).map(|k| k + round).collect();
+            assert_eq!(executor.run_workers(7, |k| k + offset), Ok(expected));
+        }
+        let result = executor.run_workers(3, |k| {
+            if k == 1 {
+                panic!("task {} failed", k);
+            }
+            k
+        });
+        assert_eq!(
+            result,
+            Err(HausdorffError::WorkerFailed("task 1 failed".to_string()))
+        );
+        // the pool survives a failed task
+        assert_eq!(executor.run_workers(2, |k| k), Ok(vec![0, 1]));
+        assert_eq!(executor.run_workers(0, |k| k), Ok(vec![]));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn rayon_tasks() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        for backend in [Backend::Rayon, Backend::RayonPool(pool.into())] {
+            assert_eq!(backend.run_workers(5, |k| k * 2), Ok(vec![0, 2, 4, 6, 8]));
+            let result = backend.run_workers(3, |k| {
+                if k == 2 {
+                    panic!("task {} failed", k);
+                }
+                k
+            });
+            assert_eq!(
+                result,
+                Err(HausdorffError::WorkerFailed("task 2 failed".to_string()))
+            );
+        }
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(3)
+            .build()
+            .unwrap();
+        assert_eq!(Backend::RayonPool(pool.into()).available_workers(), 3);
+    }
+}
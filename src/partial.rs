@@ -0,0 +1,568 @@
+//! The partial Hausdorff distance, a ranked (rather than
+//! the largest) nearest neighbour distance, and the points
+//! with the largest nearest neighbour distances.
+
+use crate::coordinate::{Accumulator, Coordinate};
+use crate::error::HausdorffError;
+use crate::input::{apply_non_finite_policy, check_shapes};
+use crate::metric::Metric;
+use crate::nearest::nearest_neighbour;
+use crate::options::HausdorffOptions;
+use crate::parallel::{estimated_work, Batches, Executor};
+use crate::result::HausdorffResult;
+use crate::shuffle::RowOrders;
+use ndarray::{ArrayView2, AsArray, Ix2};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// Which nearest neighbour distance a partial Hausdorff distance
+/// reports, constructed with [`Partial::quantile`] or
+/// [`Partial::kth_largest`].
+///
+/// Ties are broken by the order in which the rows are visited (see
+/// [`Shuffle`](crate::Shuffle)), as for the directed distance, so that
+/// `Partial::kth_largest(1)` reproduces [`try_directed_hausdorff_with`](crate::try_directed_hausdorff_with)
+/// exactly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Partial {
+    rank: Rank,
+    strategy: PartialStrategy,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Rank {
+    Quantile(f64),
+    KthLargest(usize),
+}
+
+/// How a partial Hausdorff distance is found. Both
+/// strategies report exactly the same result.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PartialStrategy {
+    /// Keep the `k` largest nearest neighbour distances found so far,
+    /// and abandon the search of any row as soon as it is closer to a
+    /// point than the smallest of them, like the early break of the
+    /// directed distance. Only those `k` distances are ever sorted.
+    #[default]
+    Pruned,
+    /// Find the nearest neighbour of every row, then select the
+    /// `k`-th largest of all the distances. Mostly useful as a
+    /// reference for the pruned search.
+    Exhaustive,
+}
+
+impl Partial {
+    /// The `q`-th quantile of the nearest neighbour distances of the
+    /// rows of `ar1`, by the nearest rank definition, i.e., the
+    /// `⌈q n⌉`-th smallest of the `n` distances (or the smallest for
+    /// `q = 0`). `Partial::quantile(0.95)` is the HD95 distance of
+    /// segmentation benchmarks, and `q = 1` the directed distance.
+    ///
+    /// Unlike e.g. `numpy.percentile`, the distances are not
+    /// interpolated, so the result is always realised by a pair of
+    /// points. A `q` outside of `[0, 1]` is reported as
+    /// [`HausdorffError::InvalidRank`].
+    pub fn quantile(q: f64) -> Self {
+        Partial {
+            rank: Rank::Quantile(q),
+            strategy: PartialStrategy::default(),
+        }
+    }
+
+    /// The `k`-th largest nearest neighbour distance of the rows of
+    /// `ar1`, counting from `k = 1` for the directed distance. A `k`
+    /// of zero, or more than the number of rows, is reported as
+    /// [`HausdorffError::InvalidRank`].
+    pub fn kth_largest(k: usize) -> Self {
+        Partial {
+            rank: Rank::KthLargest(k),
+            strategy: PartialStrategy::default(),
+        }
+    }
+
+    /// How the distance is found, by default [`PartialStrategy::Pruned`].
+    pub fn strategy(mut self, strategy: PartialStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// The rank `k`, from the largest, among `rows` distances.
+    fn kth_largest_of(self, rows: usize) -> Result<usize, HausdorffError> {
+        match self.rank {
+            Rank::Quantile(q) => Ok(rows - nearest_rank(q, rows)? + 1),
+            Rank::KthLargest(k) if (1..=rows).contains(&k) => Ok(k),
+            Rank::KthLargest(k) => Err(HausdorffError::InvalidRank(format!(
+                "k = {} is not between 1 and the {} rows",
+                k, rows
+            ))),
+        }
+    }
+}
+
+/// The rank `k`, from the smallest, of the `q`-th quantile
+/// of `rows` values by the nearest rank definition.
+pub(crate) fn nearest_rank(q: f64, rows: usize) -> Result<usize, HausdorffError> {
+    if !(0.0..=1.0).contains(&q) {
+        return Err(HausdorffError::InvalidRank(format!(
+            "the quantile {} is not between 0 and 1",
+            q
+        )));
+    }
+    Ok(((q * rows as f64).ceil() as usize).clamp(1, rows))
+}
+
+/// Calculate the partial Hausdorff distance from `ar1` to `ar2`, a
+/// ranked nearest neighbour distance of the rows of `ar1` chosen by
+/// the [`Partial`], as configured by [`HausdorffOptions`].
+///
+/// Where the directed distance is the largest of the distances from
+/// the rows of `ar1` to their nearest neighbours in `ar2`, and so is
+/// dominated by a single outlier, e.g. the 95th percentile is not.
+/// The rows are distributed over the workers in batches, as for the
+/// directed distance. Rows skipped by the [`NonFinitePolicy`](crate::NonFinitePolicy)
+/// do not count towards the ranks.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// // 20 points a unit above a line of points, but for one outlier
+/// let mut a1 = Array2::from_shape_fn((20, 2), |(i, c)| if c == 0 { i as f64 } else { 1.0 });
+/// let a2 = Array2::from_shape_fn((20, 2), |(i, c)| if c == 0 { i as f64 } else { 0.0 });
+/// a1[[7, 1]] = 50.0;
+/// let options = HausdorffOptions::new();
+/// let result = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+/// assert_eq!(result.distance, 50.0);
+/// let hd95 = try_directed_partial_hausdorff_with(&a1, &a2, Partial::quantile(0.95), &options);
+/// assert_eq!(hd95.unwrap().distance, 1.0);
+/// let second = try_directed_partial_hausdorff_with(&a1, &a2, Partial::kth_largest(2), &options);
+/// assert_eq!(second.unwrap().distance, 1.0);
+/// ```
+pub fn try_directed_partial_hausdorff_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    partial: Partial,
+    options: &HausdorffOptions<M>,
+) -> Result<HausdorffResult<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    partial_with(
+        ar1.into(),
+        ar2.into(),
+        partial,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// The partial calculation behind both [`try_directed_partial_hausdorff_with`]
+/// and [`HausdorffEngine::partial`](crate::HausdorffEngine::partial).
+pub(crate) fn partial_with<T: Coordinate, M: Metric<T>>(
+    ar1: ArrayView2<T>,
+    ar2: ArrayView2<T>,
+    partial: Partial,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+    orders: &mut RowOrders,
+) -> Result<HausdorffResult<T>, HausdorffError> {
+    check_shapes(&ar1, &ar2)?;
+    let metric = &options.metric;
+    metric.check_columns(ar1.ncols())?;
+    orders.prepare_forward(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    let RowOrders {
+        rows1, inner_rows2, ..
+    } = orders;
+    let policy = options.non_finite_policy;
+    let propagated = apply_non_finite_policy(&ar1, &ar2, metric, policy, rows1, inner_rows2)?;
+    // the rank is checked against the rows that remain
+    let k = partial.kth_largest_of(rows1.len())?;
+    if let Some(result) = propagated {
+        return Ok(result);
+    }
+    let (rows, inner_rows) = (&rows1[..], &inner_rows2[..]);
+
+    let work = estimated_work(rows.len(), inner_rows.len(), ar1.ncols());
+    let available = executor.available_workers();
+    let workers = options.workers.resolve(rows.len(), work, available);
+    let batches = Batches::new(rows.len(), workers);
+    let search = |_| match partial.strategy {
+        PartialStrategy::Pruned => {
+            largest_batches(&ar1, &ar2, metric, rows, inner_rows, &batches, k)
+                .into_iter()
+                .map(|Reverse(ranked)| ranked)
+                .collect()
+        }
+        PartialStrategy::Exhaustive => {
+            nearest_batches(&ar1, &ar2, metric, rows, inner_rows, &batches)
+        }
+    };
+    let mut ranked: Vec<_> = if workers <= 1 {
+        // single thread/serial approach
+        search(0)
+    } else {
+        executor.run_workers(workers, search)?.concat()
+    };
+    // at least k rows remain, since a worker only
+    // abandons rows once it has ranked k others
+    let (_, kth, _) = ranked.select_nth_unstable_by(k - 1, |a, b| b.cmp(a));
+    let Ranked { value, i, j, .. } = *kth;
+    Ok(HausdorffResult::new(&ar1, &ar2, metric, value, i, j))
+}
+
+/// Find the `k` rows of `ar1` farthest from `ar2`, i.e. with the
+/// largest nearest neighbour distances, as configured by
+/// [`HausdorffOptions`].
+///
+/// Returns a result for each of them, as for the directed Hausdorff
+/// distance, from the farthest to the nearest, so that the first
+/// is exactly [`try_directed_hausdorff_with`](crate::try_directed_hausdorff_with).
+/// Ties are broken by the order in which the rows are visited, as for
+/// [`Partial`]. Fewer results are returned if `ar1` has fewer than
+/// `k` rows (not counting those skipped by the
+/// [`NonFinitePolicy`](crate::NonFinitePolicy)), and only the NaN
+/// result if non-finite values propagate.
+///
+/// Each worker keeps the `k` largest distances it has found and
+/// abandons the search of any row as soon as it is closer to a point
+/// than the smallest of them, as for [`PartialStrategy::Pruned`].
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0, 1.0], [1.0, 5.0], [2.0, 2.0], [3.0, 3.0]]);
+/// let a2 = arr2(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]);
+/// let options = HausdorffOptions::new();
+/// let farthest = try_farthest_points_with(&a1, &a2, 2, &options).unwrap();
+/// assert_eq!(farthest[0].ar1_index, 1);
+/// assert_eq!(farthest[0].distance, 5.0);
+/// assert_eq!(farthest[1].ar1_index, 3);
+/// assert_eq!(farthest[1].distance, 3.0);
+/// ```
+pub fn try_farthest_points_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    k: usize,
+    options: &HausdorffOptions<M>,
+) -> Result<Vec<HausdorffResult<T>>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    farthest_with(
+        ar1.into(),
+        ar2.into(),
+        k,
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// The calculation behind both [`try_farthest_points_with`] and
+/// [`HausdorffEngine::farthest_points`](crate::HausdorffEngine::farthest_points).
+pub(crate) fn farthest_with<T: Coordinate, M: Metric<T>>(
+    ar1: ArrayView2<T>,
+    ar2: ArrayView2<T>,
+    k: usize,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+    orders: &mut RowOrders,
+) -> Result<Vec<HausdorffResult<T>>, HausdorffError> {
+    check_shapes(&ar1, &ar2)?;
+    let metric = &options.metric;
+    metric.check_columns(ar1.ncols())?;
+    orders.prepare_forward(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    let RowOrders {
+        rows1, inner_rows2, ..
+    } = orders;
+    let policy = options.non_finite_policy;
+    if let Some(result) = apply_non_finite_policy(&ar1, &ar2, metric, policy, rows1, inner_rows2)? {
+        return Ok(if k == 0 { Vec::new() } else { vec![result] });
+    }
+    let (rows, inner_rows) = (&rows1[..], &inner_rows2[..]);
+    let k = k.min(rows.len());
+    if k == 0 {
+        return Ok(Vec::new());
+    }
+
+    let work = estimated_work(rows.len(), inner_rows.len(), ar1.ncols());
+    let available = executor.available_workers();
+    let workers = options.workers.resolve(rows.len(), work, available);
+    let batches = Batches::new(rows.len(), workers);
+    let search = |_| largest_batches(&ar1, &ar2, metric, rows, inner_rows, &batches, k);
+    let mut largest: Vec<_> = if workers <= 1 {
+        // single thread/serial approach
+        search(0).into_vec()
+    } else {
+        let heaps = executor.run_workers(workers, search)?;
+        heaps.into_iter().flatten().collect()
+    };
+    // the k largest overall are among the k largest of every worker
+    largest.sort_unstable();
+    Ok(largest
+        .into_iter()
+        .take(k)
+        .map(|Reverse(Ranked { value, i, j, .. })| {
+            HausdorffResult::new(&ar1, &ar2, metric, value, i, j)
+        })
+        .collect())
+}
+
+/// A nearest neighbour distance (reduced), ordered by its value and
+/// then by the position of its row in the visiting order, with the
+/// rows of `ar1` and `ar2` realising it.
+#[derive(Clone, Copy, Debug)]
+struct Ranked<A> {
+    value: A,
+    position: usize,
+    i: usize,
+    j: usize,
+}
+
+impl<A: PartialOrd> Ord for Ranked<A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // the distances are never NaN here, having
+        // been rejected, skipped or propagated
+        self.value
+            .partial_cmp(&other.value)
+            .unwrap_or(Ordering::Equal)
+            .then(self.position.cmp(&other.position))
+    }
+}
+
+impl<A: PartialOrd> PartialOrd for Ranked<A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<A: PartialOrd> PartialEq for Ranked<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<A: PartialOrd> Eq for Ranked<A> {}
+
+/// The `k` largest nearest neighbour distances of the rows of `ar1`
+/// in the batches this worker pulls from `batches`. Rows are abandoned
+/// once closer to a point than the smallest of `k` distances already
+/// found, which is itself no larger than the `k`-th largest overall.
+fn largest_batches<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+    rows: &[usize],
+    inner_rows: &[usize],
+    batches: &Batches,
+    k: usize,
+) -> BinaryHeap<Reverse<Ranked<T::Accumulator>>> {
+    // a min heap, with the smallest of the k largest on top
+    let mut largest: BinaryHeap<Reverse<Ranked<_>>> = BinaryHeap::with_capacity(k + 1);
+    while let Some(batch) = batches.next_batch() {
+        for position in batch {
+            let bound = match largest.peek() {
+                Some(Reverse(smallest)) if largest.len() == k => smallest.value,
+                _ => T::Accumulator::ZERO,
+            };
+            let i = rows[position];
+            if let Some((value, j)) = nearest_neighbour(ar1, ar2, metric, i, inner_rows, bound) {
+                largest.push(Reverse(Ranked {
+                    value,
+                    position,
+                    i,
+                    j,
+                }));
+                if largest.len() > k {
+                    largest.pop();
+                }
+            }
+        }
+    }
+    largest
+}
+
+/// The nearest neighbour distances of all the rows of `ar1`
+/// in the batches this worker pulls from `batches`.
+fn nearest_batches<T: Coordinate, M: Metric<T>>(
+    ar1: &ArrayView2<T>,
+    ar2: &ArrayView2<T>,
+    metric: &M,
+    rows: &[usize],
+    inner_rows: &[usize],
+    batches: &Batches,
+) -> Vec<Ranked<T::Accumulator>> {
+    let mut ranked = Vec::new();
+    while let Some(batch) = batches.next_batch() {
+        for position in batch {
+            let i = rows[position];
+            let zero = T::Accumulator::ZERO;
+            let (value, j) =
+                nearest_neighbour(ar1, ar2, metric, i, inner_rows, zero).expect("no bound");
+            ranked.push(Ranked {
+                value,
+                position,
+                i,
+                j,
+            });
+        }
+    }
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{try_directed_hausdorff_with, HausdorffEngine, NonFinitePolicy, Shuffle};
+    use ndarray::prelude::*;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn matches_sorted_distances() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(18);
+        // small integer coordinates, for plenty of ties
+        let a1 = Array2::from_shape_fn((300, 2), |_| rng.gen_range(0_i32..30));
+        let a2 = Array2::from_shape_fn((50, 2), |_| rng.gen_range(0_i32..30));
+        let mut sorted: Vec<_> = a1
+            .rows()
+            .into_iter()
+            .map(|x| {
+                a2.rows()
+                    .into_iter()
+                    .map(|y| (&x - &y).mapv(|c| c as i64 * c as i64).sum() as u128)
+                    .min()
+                    .unwrap()
+            })
+            .collect();
+        sorted.sort_unstable();
+        let ranks = [
+            (Partial::quantile(0.0), sorted[0]),
+            (Partial::quantile(0.5), sorted[149]),
+            (Partial::quantile(0.95), sorted[284]),
+            (Partial::quantile(1.0), sorted[299]),
+            (Partial::kth_largest(1), sorted[299]),
+            (Partial::kth_largest(7), sorted[293]),
+            (Partial::kth_largest(300), sorted[0]),
+        ];
+        for shuffle in [Shuffle::default(), Shuffle::SciPy(2), Shuffle::Disabled] {
+            let serial = HausdorffOptions::new().workers(1).shuffle(shuffle);
+            for (partial, expected) in ranks {
+                let exhaustive = partial.strategy(PartialStrategy::Exhaustive);
+                let reference =
+                    try_directed_partial_hausdorff_with(&a1, &a2, exhaustive, &serial).unwrap();
+                assert_eq!(reference.reduced_distance, expected);
+                for workers in [1, 2, 5] {
+                    let options = serial.clone().workers(workers);
+                    for partial in [partial, exhaustive] {
+                        let actual =
+                            try_directed_partial_hausdorff_with(&a1, &a2, partial, &options);
+                        assert_eq!(actual, Ok(reference.clone()));
+                    }
+                    let mut engine = HausdorffEngine::new(options);
+                    assert_eq!(engine.partial(&a1, &a2, partial), Ok(reference.clone()));
+                }
+            }
+            // including the witness in case of ties
+            let directed = try_directed_hausdorff_with(&a1, &a2, &serial);
+            let largest =
+                try_directed_partial_hausdorff_with(&a1, &a2, Partial::kth_largest(1), &serial);
+            assert_eq!(largest, directed);
+        }
+    }
+
+    #[test]
+    fn farthest_points() {
+        let mut rng = rand_chacha::ChaChaRng::seed_from_u64(22);
+        // small integer coordinates, for plenty of ties
+        let a1 = Array2::from_shape_fn((200, 2), |_| rng.gen_range(0_i32..20));
+        let a2 = Array2::from_shape_fn((40, 2), |_| rng.gen_range(0_i32..20));
+        for shuffle in [Shuffle::default(), Shuffle::SciPy(4), Shuffle::Disabled] {
+            let serial = HausdorffOptions::new().workers(1).shuffle(shuffle);
+            // the ranked results, with the same witnesses in case of ties
+            let expected: Vec<_> = (1..=30)
+                .map(|k| {
+                    let partial = Partial::kth_largest(k).strategy(PartialStrategy::Exhaustive);
+                    try_directed_partial_hausdorff_with(&a1, &a2, partial, &serial).unwrap()
+                })
+                .collect();
+            for workers in [1, 2, 5] {
+                let options = serial.clone().workers(workers);
+                for k in [1, 7, 30] {
+                    let actual = try_farthest_points_with(&a1, &a2, k, &options);
+                    assert_eq!(actual, Ok(expected[..k].to_vec()));
+                }
+            }
+        }
+        // every row, for k beyond the number of rows
+        let options = HausdorffOptions::new().workers(3);
+        let all = try_farthest_points_with(&a1, &a2, 500, &options).unwrap();
+        assert_eq!(all.len(), 200);
+        assert!(all.windows(2).all(|w| w[0].distance >= w[1].distance));
+        assert_eq!(
+            try_farthest_points_with(&a1, &a2, 0, &options),
+            Ok(Vec::new())
+        );
+        let a1 = arr2(&[[0.0, 0.0], [f64::NAN, 1.0], [3.0, 4.0]]);
+        let a2 = arr2(&[[0.0, 0.0]]);
+        let options = options.non_finite_policy(NonFinitePolicy::Propagate);
+        let actual = try_farthest_points_with(&a1, &a2, 2, &options).unwrap();
+        assert_eq!(actual.len(), 1);
+        assert!(actual[0].distance.is_nan());
+        let options = options.non_finite_policy(NonFinitePolicy::Skip);
+        let actual = try_farthest_points_with(&a1, &a2, 5, &options).unwrap();
+        let rows: Vec<_> = actual.iter().map(|result| result.ar1_index).collect();
+        assert_eq!(rows, [2, 0]);
+    }
+
+    #[test]
+    fn farthest_points_scipy() {
+        // SciPy's farthest point of a2 first, then the next
+        // farthest point, [10, 9], which is √53 from [3, 7]
+        let (a1, a2) = crate::fixtures::indices();
+        let options = HausdorffOptions::new();
+        let actual = try_farthest_points_with(&a2, &a1, 2, &options).unwrap();
+        let actual: Vec<_> = actual.into_iter().map(|r| r.into_tuple()).collect();
+        let expected = [crate::fixtures::INDICES_REVERSE, (53_f64.sqrt(), 3, 3)];
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn invalid_ranks() {
+        let a1 = arr2(&[[0.0, 0.0], [f64::NAN, 1.0], [3.0, 4.0]]);
+        let a2 = arr2(&[[0.0, 0.0]]);
+        let options = HausdorffOptions::new().non_finite_policy(NonFinitePolicy::Skip);
+        let partial = |partial| try_directed_partial_hausdorff_with(&a1, &a2, partial, &options);
+        let invalid = |reason: &str| Err(HausdorffError::InvalidRank(reason.to_string()));
+        assert_eq!(partial(Partial::kth_largest(2)).unwrap().distance, 0.0);
+        // the skipped row does not count
+        assert_eq!(
+            partial(Partial::kth_largest(3)),
+            invalid("k = 3 is not between 1 and the 2 rows")
+        );
+        assert_eq!(
+            partial(Partial::kth_largest(0)),
+            invalid("k = 0 is not between 1 and the 2 rows")
+        );
+        assert_eq!(
+            partial(Partial::quantile(1.5)),
+            invalid("the quantile 1.5 is not between 0 and 1")
+        );
+        assert!(partial(Partial::quantile(f64::NAN)).is_err());
+        let options = options.non_finite_policy(NonFinitePolicy::Propagate);
+        let actual =
+            try_directed_partial_hausdorff_with(&a1, &a2, Partial::quantile(0.5), &options);
+        assert!(actual.unwrap().distance.is_nan());
+    }
+}
@@ -0,0 +1,376 @@
+//! The structured result of a directed Hausdorff distance calculation.
+
+use crate::coordinate::{Coordinate, Distance};
+use crate::metric::Metric;
+use ndarray::{Array1, ArrayView2};
+use std::fmt;
+
+/// The directed Hausdorff distance between two arrays of points,
+/// along with the pair of points that realises it.
+///
+/// `ar1_point` is the point of the first array that is farthest
+/// from the second array, and `ar2_point` is its nearest neighbour
+/// in the second array.
+///
+/// With the `serde` feature enabled, the result may be serialized
+/// and deserialized.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "T: serde::Serialize, T::Accumulator: serde::Serialize, \
+                     Distance<T>: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, T::Accumulator: serde::Deserialize<'de>, \
+                       Distance<T>: serde::Deserialize<'de>"
+    ))
+)]
+pub struct HausdorffResult<T: Coordinate> {
+    /// The directed Hausdorff distance.
+    pub distance: Distance<T>,
+    /// The reduced distance of the [`Metric`], as accumulated before
+    /// the final transform, i.e., the square of the distance for the
+    /// default Euclidean metric (exact for integer coordinates).
+    pub reduced_distance: T::Accumulator,
+    /// Row index of the witness point in the first array.
+    pub ar1_index: usize,
+    /// Row index, in the second array, of the nearest
+    /// neighbour of the witness point.
+    pub ar2_index: usize,
+    /// Coordinates of the witness point in the first array.
+    pub ar1_point: Array1<T>,
+    /// Coordinates of the nearest neighbour in the second array.
+    pub ar2_point: Array1<T>,
+}
+
+impl<T: Coordinate> HausdorffResult<T> {
+    pub(crate) fn new<M: Metric<T>>(
+        ar1: &ArrayView2<T>,
+        ar2: &ArrayView2<T>,
+        metric: &M,
+        reduced_distance: T::Accumulator,
+        ar1_index: usize,
+        ar2_index: usize,
+    ) -> Self {
+        HausdorffResult {
+            distance: metric.distance(reduced_distance),
+            reduced_distance,
+            ar1_index,
+            ar2_index,
+            ar1_point: ar1.row(ar1_index).to_owned(),
+            ar2_point: ar2.row(ar2_index).to_owned(),
+        }
+    }
+
+    /// The `(distance, ar1_index, ar2_index)` tuple returned
+    /// by [`directed_hausdorff`](crate::directed_hausdorff).
+    pub fn into_tuple(self) -> (Distance<T>, usize, usize) {
+        (self.distance, self.ar1_index, self.ar2_index)
+    }
+}
+
+impl<T> fmt::Display for HausdorffResult<T>
+where
+    T: Coordinate + fmt::Display,
+    Distance<T>: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} between ar1[{}] = {} and ar2[{}] = {}",
+            self.distance, self.ar1_index, self.ar1_point, self.ar2_index, self.ar2_point
+        )
+    }
+}
+
+/// The direction of a directed Hausdorff distance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    /// From the points of `ar1` to their nearest neighbours in `ar2`.
+    Forward,
+    /// From the points of `ar2` to their nearest neighbours in `ar1`.
+    Reverse,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Forward => write!(f, "ar1 -> ar2"),
+            Direction::Reverse => write!(f, "ar2 -> ar1"),
+        }
+    }
+}
+
+/// The (symmetric) Hausdorff distance between two arrays of points,
+/// the larger of the two directed distances.
+///
+/// The `reverse` result is the directed distance from `ar2` to `ar1`,
+/// so its `ar1_*` fields refer to `ar2` and vice versa, as if
+/// the arrays had been swapped. It is `None` only when symmetric
+/// pruning is enabled and the reverse direction was proven not to
+/// exceed the forward one before it was calculated exactly.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "T: serde::Serialize, T::Accumulator: serde::Serialize, \
+                     Distance<T>: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, T::Accumulator: serde::Deserialize<'de>, \
+                       Distance<T>: serde::Deserialize<'de>"
+    ))
+)]
+pub struct SymmetricHausdorffResult<T: Coordinate> {
+    /// The Hausdorff distance.
+    pub distance: Distance<T>,
+    /// The direction realising the distance, preferring
+    /// [`Direction::Forward`] in case of ties.
+    pub direction: Direction,
+    /// The directed distance from `ar1` to `ar2`.
+    pub forward: HausdorffResult<T>,
+    /// The directed distance from `ar2` to `ar1`, unless pruned.
+    pub reverse: Option<HausdorffResult<T>>,
+}
+
+impl<T: Coordinate> SymmetricHausdorffResult<T> {
+    pub(crate) fn new(forward: HausdorffResult<T>, reverse: Option<HausdorffResult<T>>) -> Self {
+        let direction = match &reverse {
+            Some(reverse) if reverse.reduced_distance > forward.reduced_distance => {
+                Direction::Reverse
+            }
+            _ => Direction::Forward,
+        };
+        let distance = match (&direction, &reverse) {
+            (Direction::Reverse, Some(reverse)) => reverse.distance,
+            _ => forward.distance,
+        };
+        SymmetricHausdorffResult {
+            distance,
+            direction,
+            forward,
+            reverse,
+        }
+    }
+
+    /// The directed result realising the distance.
+    pub fn witness(&self) -> &HausdorffResult<T> {
+        match (&self.direction, &self.reverse) {
+            (Direction::Reverse, Some(reverse)) => reverse,
+            _ => &self.forward,
+        }
+    }
+}
+
+impl<T> fmt::Display for SymmetricHausdorffResult<T>
+where
+    T: Coordinate + fmt::Display,
+    Distance<T>: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}): {}",
+            self.distance,
+            self.direction,
+            self.witness()
+        )
+    }
+}
+
+/// Whether a Hausdorff distance is within a tolerance, as decided by
+/// [`try_hausdorff_within_with`](crate::try_hausdorff_within_with)
+/// or [`try_directed_hausdorff_within_with`](crate::try_directed_hausdorff_within_with).
+///
+/// With the `serde` feature enabled, the decision may be serialized
+/// and deserialized.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "T: serde::Serialize, T::Accumulator: serde::Serialize, \
+                     Distance<T>: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, T::Accumulator: serde::Deserialize<'de>, \
+                       Distance<T>: serde::Deserialize<'de>"
+    ))
+)]
+pub enum Decision<T: Coordinate> {
+    /// Every point is within the tolerance of the other array.
+    Within,
+    /// Some point is farther than the tolerance from the other array.
+    Beyond {
+        /// The direction in which the point was found, i.e.,
+        /// [`Direction::Reverse`] for a point of `ar2`.
+        direction: Direction,
+        /// The point and its nearest neighbour, with the `ar1_*` fields
+        /// referring to `ar2` in the reverse direction, as for
+        /// [`SymmetricHausdorffResult::reverse`].
+        witness: HausdorffResult<T>,
+    },
+}
+
+impl<T: Coordinate> Decision<T> {
+    /// Whether every point is within the tolerance.
+    pub fn is_within(&self) -> bool {
+        matches!(self, Decision::Within)
+    }
+}
+
+/// A Hausdorff distance calculated up to a cutoff, as by
+/// [`try_hausdorff_bounded_with`](crate::try_hausdorff_bounded_with)
+/// or [`try_directed_hausdorff_bounded_with`](crate::try_directed_hausdorff_bounded_with).
+///
+/// With the `serde` feature enabled, the outcome may be serialized
+/// and deserialized.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "T: serde::Serialize, T::Accumulator: serde::Serialize, \
+                     Distance<T>: serde::Serialize, R: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>, T::Accumulator: serde::Deserialize<'de>, \
+                       Distance<T>: serde::Deserialize<'de>, R: serde::Deserialize<'de>"
+    ))
+)]
+pub enum Bounded<T: Coordinate, R = HausdorffResult<T>> {
+    /// The distance is at most the cutoff, with the
+    /// same result as without one.
+    Exact(R),
+    /// The distance exceeds the cutoff.
+    ExceedsCutoff {
+        /// The direction in which the cutoff was exceeded, i.e.,
+        /// [`Direction::Reverse`] for a point of `ar2`.
+        direction: Direction,
+        /// The farthest point found before the calculation stopped, and
+        /// its nearest neighbour, with a distance beyond the cutoff but
+        /// no larger than the exact distance. The `ar1_*` fields refer to
+        /// `ar2` in the reverse direction, as for
+        /// [`SymmetricHausdorffResult::reverse`].
+        lower_bound: HausdorffResult<T>,
+    },
+}
+
+impl<T: Coordinate, R> Bounded<T, R> {
+    /// Whether the distance is at most the cutoff.
+    pub fn is_exact(&self) -> bool {
+        matches!(self, Bounded::Exact(_))
+    }
+}
+
+/// The points of the first array that are farther than a tolerance
+/// from the second array, as found by
+/// [`try_nearest_neighbours_beyond_with`](crate::try_nearest_neighbours_beyond_with).
+///
+/// With the `serde` feature enabled, the result may be serialized
+/// and deserialized.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "Distance<T>: serde::Serialize",
+        deserialize = "Distance<T>: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Outliers<T: Coordinate> {
+    /// Row indices of the points in the first array, in increasing order.
+    pub rows: Array1<usize>,
+    /// The distance from each point to its nearest neighbour.
+    pub distances: Array1<Distance<T>>,
+    /// Row index of each nearest neighbour in the second array, or
+    /// `usize::MAX` for a point with a NaN distance, which has none.
+    pub neighbours: Array1<usize>,
+}
+
+/// The distribution of the distances from the points of the first
+/// array to their nearest neighbours in the second, as found by
+/// [`try_directed_distance_distribution_with`](crate::try_directed_distance_distribution_with).
+///
+/// With the `serde` feature enabled, the result may be serialized
+/// and deserialized.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DistanceDistribution {
+    /// The edges of the bins of the [`Histogram`](crate::Histogram).
+    pub edges: Array1<f64>,
+    /// The number of distances in each bin.
+    pub counts: Array1<usize>,
+    /// The number of distances below the first bin.
+    pub below: usize,
+    /// The number of distances above the last bin.
+    pub above: usize,
+    /// The mean distance.
+    pub mean: f64,
+    /// The root mean square distance.
+    pub rms: f64,
+    /// The median distance, the `0.5` quantile by the nearest rank
+    /// definition, i.e., the lower of the middle two distances
+    /// for an even number of points.
+    pub median: f64,
+    /// The largest distance, the directed Hausdorff distance.
+    pub max: f64,
+    /// The quantiles of the [`Histogram`](crate::Histogram),
+    /// in the order they were given.
+    pub quantiles: Array1<f64>,
+}
+
+impl DistanceDistribution {
+    /// The cumulative distribution, i.e., the number of distances
+    /// up to the upper edge of each bin, including those below
+    /// the first bin.
+    pub fn cumulative_counts(&self) -> Array1<usize> {
+        let mut total = self.below;
+        self.counts.mapv(|count| {
+            total += count;
+            total
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metric::Euclidean;
+    use ndarray::prelude::*;
+
+    #[test]
+    fn witness_points() {
+        let a1 = arr2(&[[0_u16, 0], [3, 4]]);
+        let a2 = arr2(&[[0_u16, 0]]);
+        let result = HausdorffResult::new(&a1.view(), &a2.view(), &Euclidean, 25, 1, 0);
+        assert_eq!(result.distance, 5.0);
+        assert_eq!(result.reduced_distance, 25);
+        assert_eq!(result.ar1_point, arr1(&[3, 4]));
+        assert_eq!(result.ar2_point, arr1(&[0, 0]));
+        assert_eq!(result.clone().into_tuple(), (5.0, 1, 0));
+        assert_eq!(
+            result.to_string(),
+            "5 between ar1[1] = [3, 4] and ar2[0] = [0, 0]"
+        );
+    }
+
+    #[test]
+    fn symmetric_direction() {
+        let a1 = arr2(&[[0_u16, 0], [3, 4]]);
+        let a2 = arr2(&[[0_u16, 0]]);
+        let forward = HausdorffResult::new(&a1.view(), &a2.view(), &Euclidean, 25, 1, 0);
+        let reverse = HausdorffResult::new(&a2.view(), &a1.view(), &Euclidean, 0, 0, 0);
+        let result = SymmetricHausdorffResult::new(forward.clone(), Some(reverse.clone()));
+        assert_eq!(result.direction, Direction::Forward);
+        assert_eq!(result.distance, 5.0);
+        assert_eq!(result.witness(), &forward);
+        let result = SymmetricHausdorffResult::new(reverse.clone(), Some(forward.clone()));
+        assert_eq!(result.direction, Direction::Reverse);
+        assert_eq!(result.distance, 5.0);
+        assert_eq!(result.witness(), &forward);
+        assert_eq!(
+            result.to_string(),
+            "5 (ar2 -> ar1): 5 between ar1[1] = [3, 4] and ar2[0] = [0, 0]"
+        );
+        let result = SymmetricHausdorffResult::new(forward.clone(), None);
+        assert_eq!(result.direction, Direction::Forward);
+        assert_eq!(result.witness(), &forward);
+    }
+}
@@ -0,0 +1,280 @@
+//! Orderings in which the rows of the two arrays are visited.
+//!
+//! The early break algorithm is exact for any ordering, but breaks
+//! early more often when the data is not "structured", so the rows
+//! are shuffled by default.
+
+use crate::error::HausdorffError;
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+/// How the rows of the two arrays are ordered before searching.
+///
+/// The ordering never changes the distance, but it does change the
+/// running time, and, when several pairs of points realise the
+/// distance, which of them is reported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Shuffle {
+    /// Shuffle both arrays with a ChaCha generator seeded by the
+    /// given bytes. The default seed of all zeros is the ordering
+    /// this crate has always used.
+    ChaCha([u8; 32]),
+    /// Shuffle both arrays as `scipy.spatial.distance.directed_hausdorff`
+    /// does for an integer `seed` argument, using the Mersenne Twister
+    /// of NumPy's legacy `RandomState`, so that the reported indices
+    /// match SciPy exactly.
+    SciPy(u32),
+    /// Visit the rows in their stored order.
+    Disabled,
+    /// Visit the rows in the caller provided orders, which must
+    /// be permutations of the row indices of each array.
+    Permutation { ar1: Vec<usize>, ar2: Vec<usize> },
+}
+
+impl Default for Shuffle {
+    fn default() -> Self {
+        Shuffle::ChaCha([0; 32])
+    }
+}
+
+impl Shuffle {
+    /// The orders in which the rows of arrays with
+    /// `ar1_rows` and `ar2_rows` rows are visited.
+    pub(crate) fn orderings(
+        &self,
+        ar1_rows: usize,
+        ar2_rows: usize,
+    ) -> Result<(Vec<usize>, Vec<usize>), HausdorffError> {
+        let mut order1: Vec<usize> = (0..ar1_rows).collect();
+        let mut order2: Vec<usize> = (0..ar2_rows).collect();
+        match self {
+            Shuffle::ChaCha(seed) => {
+                let mut rng = ChaChaRng::from_seed(*seed);
+                let mut rng2 = ChaChaRng::from_seed(*seed);
+                order1.shuffle(&mut rng);
+                order2.shuffle(&mut rng2);
+            }
+            Shuffle::SciPy(seed) => {
+                // SciPy shuffles both index arrays
+                // with a single RandomState, in turn
+                let mut rng = Mt19937::new(*seed);
+                rng.shuffle(&mut order1);
+                rng.shuffle(&mut order2);
+            }
+            Shuffle::Disabled => {}
+            Shuffle::Permutation { ar1, ar2 } => {
+                check_permutation("ar1", ar1, ar1_rows)?;
+                check_permutation("ar2", ar2, ar2_rows)?;
+                order1.copy_from_slice(ar1);
+                order2.copy_from_slice(ar2);
+            }
+        }
+        Ok((order1, order2))
+    }
+
+    /// The orders for the reverse direction, in which the rows of
+    /// `ar2` are searched for their nearest neighbours in `ar1`,
+    /// matching a directed calculation with the arrays swapped.
+    pub(crate) fn reverse_orderings(
+        &self,
+        ar1_rows: usize,
+        ar2_rows: usize,
+    ) -> Result<(Vec<usize>, Vec<usize>), HausdorffError> {
+        match self {
+            Shuffle::Permutation { ar1, ar2 } => {
+                check_permutation("ar1", ar1, ar1_rows)?;
+                check_permutation("ar2", ar2, ar2_rows)?;
+                Ok((ar2.clone(), ar1.clone()))
+            }
+            _ => self.orderings(ar2_rows, ar1_rows),
+        }
+    }
+}
+
+/// Orderings cached for arrays of the given sizes.
+type CachedOrderings = Option<((usize, usize), (Vec<usize>, Vec<usize>))>;
+
+/// The row orders of a calculation, reused by later calculations on
+/// arrays of the same sizes so that they are only shuffled once,
+/// along with working copies that the non-finite policy may filter.
+#[derive(Debug, Default)]
+pub(crate) struct RowOrders {
+    forward: CachedOrderings,
+    reverse: CachedOrderings,
+    /// The rows of `ar1` to search, and those of `ar2` to search them in.
+    pub(crate) rows1: Vec<usize>,
+    pub(crate) inner_rows2: Vec<usize>,
+    /// The rows of `ar2` to search, and those of `ar1` to search them in.
+    pub(crate) rows2: Vec<usize>,
+    pub(crate) inner_rows1: Vec<usize>,
+}
+
+impl RowOrders {
+    /// Fill `rows1` and `inner_rows2` for the forward direction.
+    pub(crate) fn prepare_forward(
+        &mut self,
+        shuffle: &Shuffle,
+        ar1_rows: usize,
+        ar2_rows: usize,
+    ) -> Result<(), HausdorffError> {
+        let (rows, inner_rows) = cached(&mut self.forward, (ar1_rows, ar2_rows), || {
+            shuffle.orderings(ar1_rows, ar2_rows)
+        })?;
+        self.rows1.clone_from(rows);
+        self.inner_rows2.clone_from(inner_rows);
+        Ok(())
+    }
+
+    /// Fill `rows2` and `inner_rows1` for the reverse direction.
+    pub(crate) fn prepare_reverse(
+        &mut self,
+        shuffle: &Shuffle,
+        ar1_rows: usize,
+        ar2_rows: usize,
+    ) -> Result<(), HausdorffError> {
+        let (rows, inner_rows) = cached(&mut self.reverse, (ar1_rows, ar2_rows), || {
+            shuffle.reverse_orderings(ar1_rows, ar2_rows)
+        })?;
+        self.rows2.clone_from(rows);
+        self.inner_rows1.clone_from(inner_rows);
+        Ok(())
+    }
+}
+
+fn cached(
+    cache: &mut CachedOrderings,
+    sizes: (usize, usize),
+    orderings: impl FnOnce() -> Result<(Vec<usize>, Vec<usize>), HausdorffError>,
+) -> Result<&(Vec<usize>, Vec<usize>), HausdorffError> {
+    if !matches!(cache, Some((cached_sizes, _)) if *cached_sizes == sizes) {
+        *cache = Some((sizes, orderings()?));
+    }
+    Ok(&cache.as_ref().expect("orderings were just cached").1)
+}
+
+fn check_permutation(
+    argument: &'static str,
+    permutation: &[usize],
+    rows: usize,
+) -> Result<(), HausdorffError> {
+    let mut seen = vec![false; rows];
+    let valid = permutation.len() == rows
+        && permutation
+            .iter()
+            .all(|&row| row < rows && !std::mem::replace(&mut seen[row], true));
+    if valid {
+        Ok(())
+    } else {
+        Err(HausdorffError::InvalidPermutation { argument, rows })
+    }
+}
+
+/// The 32 bit Mersenne Twister, seeded and sampled
+/// as by NumPy's legacy `RandomState`.
+struct Mt19937 {
+    state: [u32; 624],
+    index: usize,
+}
+
+impl Mt19937 {
+    fn new(seed: u32) -> Self {
+        let mut state = [0; 624];
+        state[0] = seed;
+        for i in 1..624 {
+            state[i] = 1812433253_u32
+                .wrapping_mul(state[i - 1] ^ (state[i - 1] >> 30))
+                .wrapping_add(i as u32);
+        }
+        Mt19937 { state, index: 624 }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        if self.index >= 624 {
+            for i in 0..624 {
+                let y = (self.state[i] & 0x8000_0000) | (self.state[(i + 1) % 624] & 0x7fff_ffff);
+                let mut next = self.state[(i + 397) % 624] ^ (y >> 1);
+                if y & 1 != 0 {
+                    next ^= 0x9908_b0df;
+                }
+                self.state[i] = next;
+            }
+            self.index = 0;
+        }
+        let mut y = self.state[self.index];
+        self.index += 1;
+        y ^= y >> 11;
+        y ^= (y << 7) & 0x9d2c_5680;
+        y ^= (y << 15) & 0xefc6_0000;
+        y ^ (y >> 18)
+    }
+
+    /// A uniform integer in `[0, max]`, by masked rejection
+    /// sampling as in NumPy's `random_interval`.
+    fn interval(&mut self, max: usize) -> usize {
+        if max == 0 {
+            return 0;
+        }
+        let mut mask = max as u64;
+        for shift in [1, 2, 4, 8, 16, 32] {
+            mask |= mask >> shift;
+        }
+        loop {
+            let value = if max as u64 <= 0xffff_ffff {
+                self.next_u32() as u64 & mask
+            } else {
+                ((self.next_u32() as u64) << 32 | self.next_u32() as u64) & mask
+            };
+            if value <= max as u64 {
+                return value as usize;
+            }
+        }
+    }
+
+    /// Fisher-Yates shuffle, in the order used by `RandomState.shuffle`.
+    fn shuffle(&mut self, values: &mut [usize]) {
+        for i in (1..values.len()).rev() {
+            let j = self.interval(i);
+            values.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mersenne_twister_matches_numpy() {
+        // np.random.RandomState(seed).random_sample()
+        // combines the first two 32 bit outputs
+        for (seed, expected) in [(0, 0.5488135039273248), (1, 0.417022004702574)] {
+            let mut rng = Mt19937::new(seed);
+            let a = (rng.next_u32() >> 5) as f64;
+            let b = (rng.next_u32() >> 6) as f64;
+            assert_eq!((a * 67108864.0 + b) / 9007199254740992.0, expected);
+        }
+        let mut rng = Mt19937::new(0);
+        assert_eq!(rng.next_u32(), 2357136044);
+        assert_eq!(rng.next_u32(), 2546248239);
+    }
+
+    #[test]
+    fn invalid_permutations() {
+        for (ar1, ar2, argument) in [
+            (vec![0, 1], vec![0, 1, 2], "ar1"),
+            (vec![0, 1, 2], vec![0, 1, 1], "ar2"),
+            (vec![0, 1, 3], vec![0, 1, 2], "ar1"),
+        ] {
+            let shuffle = Shuffle::Permutation { ar1, ar2 };
+            assert_eq!(
+                shuffle.orderings(3, 3),
+                Err(HausdorffError::InvalidPermutation { argument, rows: 3 })
+            );
+        }
+        let shuffle = Shuffle::Permutation {
+            ar1: vec![2, 0, 1],
+            ar2: vec![1, 0],
+        };
+        assert_eq!(shuffle.orderings(3, 2), Ok((vec![2, 0, 1], vec![1, 0])));
+    }
+}
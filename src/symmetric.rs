@@ -0,0 +1,288 @@
+//! The (symmetric) Hausdorff distance, calculating
+//! both directions in a single parallel pass.
+
+use crate::coordinate::{Accumulator, Coordinate};
+use crate::early_break::{directed_hausdorff_batches, merge, SharedBound};
+use crate::error::HausdorffError;
+use crate::input::{
+    apply_non_finite_policy, check_shapes, propagate_non_finite, retain_finite_rows,
+};
+use crate::metric::Metric;
+use crate::options::{HausdorffOptions, NonFinitePolicy};
+use crate::parallel::{estimated_work, Batches, Executor};
+use crate::result::{HausdorffResult, SymmetricHausdorffResult};
+use crate::shuffle::RowOrders;
+use ndarray::{ArrayView2, AsArray, Ix2};
+
+/// Calculate the (symmetric) Hausdorff distance between two arrays,
+/// the larger of the directed distances from `ar1` to `ar2` and
+/// from `ar2` to `ar1`.
+///
+/// Rather than calling [`directed_hausdorff_view`](crate::directed_hausdorff_view)
+/// twice, both directions are calculated by the same `workers`
+/// threads, each moving on to batches of the rows of `ar2` once
+/// the rows of `ar1` have all been handed out.
+///
+/// # Panics
+///
+/// Panics under the same conditions as
+/// [`directed_hausdorff_view`](crate::directed_hausdorff_view).
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[1.0, 0.0], [0.0, 1.0], [-1.0, 0.0], [0.0, -1.0]]);
+/// let a2 = arr2(&[[2.0, 0.0], [0.0, 2.0], [-2.0, 0.0], [0.0, -4.0]]);
+/// let result = hausdorff(&a1, &a2, 2);
+/// assert_eq!(result.distance, 3.0);
+/// // realised by the point (0, -4) of a2, farthest from a1
+/// assert_eq!(result.direction, Direction::Reverse);
+/// assert_eq!(result.witness().ar1_point, arr1(&[0.0, -4.0]));
+/// assert_eq!(result.forward.distance, 2.23606797749979);
+/// ```
+pub fn hausdorff<'a, 'b, T, V1, V2>(ar1: V1, ar2: V2, workers: usize) -> SymmetricHausdorffResult<T>
+where
+    T: Coordinate + 'a + 'b,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    try_hausdorff(ar1, ar2, workers).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Calculate the (symmetric) Hausdorff distance between two arrays,
+/// reporting invalid inputs as a [`HausdorffError`].
+pub fn try_hausdorff<'a, 'b, T, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    workers: usize,
+) -> Result<SymmetricHausdorffResult<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    try_hausdorff_with(ar1, ar2, &HausdorffOptions::new().workers(workers))
+}
+
+/// Calculate the (symmetric) Hausdorff distance between two arrays,
+/// as configured by [`HausdorffOptions`].
+///
+/// Each direction visits the rows in the order a directed calculation
+/// with the same options would, so the `forward` and `reverse` results
+/// match [`try_directed_hausdorff_with`](crate::try_directed_hausdorff_with)
+/// for `(ar1, ar2)` and `(ar2, ar1)` respectively.
+///
+/// With [`HausdorffOptions::symmetric_pruning`] enabled, rows of
+/// `ar2` whose nearest neighbour is closer than the running maximum of
+/// the forward direction are abandoned early, since they cannot realise
+/// the symmetric distance. The `reverse` result is then only reported
+/// if it does realise (or tie) the symmetric distance.
+///
+/// # Examples
+///
+/// ```
+/// # use rusty_hausdorff::*;
+/// # use ndarray::prelude::*;
+/// let a1 = arr2(&[[0.0, 0.0], [0.0, 10.0]]);
+/// let a2 = arr2(&[[0.0, 1.0], [0.0, 2.0], [0.0, 3.0]]);
+/// let options = HausdorffOptions::new().workers(2).symmetric_pruning(true);
+/// let result = try_hausdorff_with(&a1, &a2, &options).unwrap();
+/// assert_eq!(result.distance, 7.0);
+/// assert_eq!(result.direction, Direction::Forward);
+/// // the reverse distance (2.0) could not exceed 7.0
+/// assert_eq!(result.reverse, None);
+/// ```
+pub fn try_hausdorff_with<'a, 'b, T, M, V1, V2>(
+    ar1: V1,
+    ar2: V2,
+    options: &HausdorffOptions<M>,
+) -> Result<SymmetricHausdorffResult<T>, HausdorffError>
+where
+    T: Coordinate + 'a + 'b,
+    M: Metric<T>,
+    V1: AsArray<'a, T, Ix2>,
+    V2: AsArray<'b, T, Ix2>,
+{
+    let backend = Executor::Backend(&options.backend);
+    symmetric_with(
+        ar1.into(),
+        ar2.into(),
+        options,
+        backend,
+        &mut RowOrders::default(),
+    )
+}
+
+/// The symmetric calculation behind both [`try_hausdorff_with`]
+/// and [`HausdorffEngine::symmetric`](crate::HausdorffEngine::symmetric).
+pub(crate) fn symmetric_with<T: Coordinate, M: Metric<T>>(
+    ar1: ArrayView2<T>,
+    ar2: ArrayView2<T>,
+    options: &HausdorffOptions<M>,
+    executor: Executor,
+    orders: &mut RowOrders,
+) -> Result<SymmetricHausdorffResult<T>, HausdorffError> {
+    check_shapes(&ar1, &ar2)?;
+    let metric = &options.metric;
+    metric.check_columns(ar1.ncols())?;
+    orders.prepare_forward(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    orders.prepare_reverse(&options.shuffle, ar1.nrows(), ar2.nrows())?;
+    let RowOrders {
+        rows1,
+        inner_rows2,
+        rows2,
+        inner_rows1,
+        ..
+    } = orders;
+    let policy = options.non_finite_policy;
+    if let Some(forward) = apply_non_finite_policy(&ar1, &ar2, metric, policy, rows1, inner_rows2)?
+    {
+        let reverse = propagate_non_finite(&ar2, &ar1, metric);
+        return Ok(SymmetricHausdorffResult::new(forward, reverse));
+    }
+    if options.non_finite_policy == NonFinitePolicy::Skip {
+        retain_finite_rows("ar2", &ar2, rows2)?;
+        retain_finite_rows("ar1", &ar1, inner_rows1)?;
+    }
+
+    let prune = options.symmetric_pruning;
+    let zero = T::Accumulator::ZERO;
+    let work = estimated_work(rows1.len(), inner_rows2.len(), ar1.ncols())
+        .saturating_add(estimated_work(rows2.len(), inner_rows1.len(), ar1.ncols()));
+    let rows = rows1.len().max(rows2.len());
+    let workers = options
+        .workers
+        .resolve(rows, work, executor.available_workers());
+    let forward_batches = Batches::new(rows1.len(), workers);
+    let reverse_batches = Batches::new(rows2.len(), workers);
+    let shared = [SharedBound::new(zero), SharedBound::new(zero)];
+    let shared = (workers > 1 && options.shared_bound).then_some(&shared);
+    let both_directions = |_| {
+        let forward = directed_hausdorff_batches(
+            &ar1,
+            &ar2,
+            metric,
+            rows1,
+            inner_rows2,
+            &forward_batches,
+            zero,
+            shared.map(|[forward, _]| forward),
+        );
+        // any lower bound on the forward distance is also
+        // one on the symmetric distance
+        let mut bound = match (prune, forward) {
+            (true, Some(((cmax, _, _), _))) => cmax,
+            _ => zero,
+        };
+        if let (true, Some([forward, _])) = (prune, shared) {
+            let global = forward.load();
+            if global > bound {
+                bound = global;
+            }
+        }
+        let reverse = directed_hausdorff_batches(
+            &ar2,
+            &ar1,
+            metric,
+            rows2,
+            inner_rows1,
+            &reverse_batches,
+            bound,
+            shared.map(|[_, reverse]| reverse),
+        );
+        (forward, reverse)
+    };
+    let (forward, reverse) = if workers <= 1 {
+        // single thread/serial approach
+        let (forward, reverse) = both_directions(0);
+        (merge([forward]), merge([reverse]))
+    } else {
+        let (forward, reverse): (Vec<_>, Vec<_>) = executor
+            .run_workers(workers, both_directions)?
+            .into_iter()
+            .unzip();
+        (merge(forward), merge(reverse))
+    };
+
+    let (forward_max, i, j) = forward.expect("non-empty rows");
+    let forward = HausdorffResult::new(&ar1, &ar2, metric, forward_max, i, j);
+    // a pruned reverse maximum is only exact if
+    // it reaches the forward maximum
+    let reverse = reverse
+        .filter(|&(reverse_max, _, _)| !prune || reverse_max >= forward_max)
+        .map(|(reverse_max, i, j)| HausdorffResult::new(&ar2, &ar1, metric, reverse_max, i, j));
+    Ok(SymmetricHausdorffResult::new(forward, reverse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{try_directed_hausdorff_with, Direction, Shuffle};
+    use ndarray::prelude::*;
+
+    #[test]
+    fn matches_directed_calculations() {
+        let a1 = arr2(&[
+            [1.74817113, 0.27479074, 4.65646198],
+            [1.16174122, 2.13268615, 3.23624532],
+            [0.91969822, 4.20465196, 1.15848725],
+            [4.48290033, 2.91773345, 2.61951886],
+            [4.57078972, 1.74358378, 4.15133407],
+            [0.17069513, 3.31458363, 0.52684683],
+        ]);
+        let a2 = arr2(&[
+            [2.44051647, 3.72771419, 1.70998132],
+            [0.56762581, 1.09420038, 2.93499868],
+            [1.79280814, 1.92535183, 0.207613],
+            [3.30735955, 0.76909727, 3.25351285],
+            [2.67056764, 1.63746358, 3.70897313],
+            [2.64122702, 0.90337615, 3.38465142],
+            [1.34034943, 1.99235183, 2.26215791],
+            [1.4348432, 3.25730841, 3.66045435],
+            [-8.8803127, 3.50204148, 2.91110745],
+        ]);
+        for shuffle in [Shuffle::default(), Shuffle::SciPy(3), Shuffle::Disabled] {
+            for workers in 0..9 {
+                let options = HausdorffOptions::new()
+                    .workers(workers)
+                    .shuffle(shuffle.clone());
+                let forward = try_directed_hausdorff_with(&a1, &a2, &options).unwrap();
+                let reverse = try_directed_hausdorff_with(&a2, &a1, &options).unwrap();
+                assert!(reverse.distance > forward.distance);
+                let actual = try_hausdorff_with(&a1, &a2, &options).unwrap();
+                assert_eq!(actual.forward, forward);
+                assert_eq!(actual.reverse.as_ref(), Some(&reverse));
+                assert_eq!(actual.direction, Direction::Reverse);
+                assert_eq!(actual.distance, reverse.distance);
+                // pruning cannot change the realising direction
+                let options = options.symmetric_pruning(true);
+                assert_eq!(try_hausdorff_with(&a1, &a2, &options).unwrap(), actual);
+                let swapped = try_hausdorff_with(&a2, &a1, &options).unwrap();
+                assert_eq!(swapped.direction, Direction::Forward);
+                assert_eq!(swapped.forward, reverse);
+                assert_eq!(swapped.reverse, None);
+            }
+        }
+    }
+
+    #[test]
+    fn non_finite_policies() {
+        let a1 = arr2(&[[0., 0.], [f64::NAN, 1.], [0., 10.]]);
+        let a2 = arr2(&[[0., 1.], [0., 2.], [f64::INFINITY, 0.]]);
+        for workers in 0..4 {
+            let options = HausdorffOptions::new().workers(workers);
+            assert!(try_hausdorff_with(&a1, &a2, &options).is_err());
+            let options = options.non_finite_policy(NonFinitePolicy::Skip);
+            let actual = try_hausdorff_with(&a1, &a2, &options).unwrap();
+            assert_eq!(actual.distance, 8.0);
+            assert_eq!(actual.reverse.unwrap().distance, 2.0);
+            let options = options.non_finite_policy(NonFinitePolicy::Propagate);
+            let actual = try_hausdorff_with(&a1, &a2, &options).unwrap();
+            assert!(actual.distance.is_nan());
+            assert_eq!(actual.forward.ar1_index, 1);
+            assert_eq!(actual.reverse.unwrap().ar1_index, 2);
+        }
+    }
+}